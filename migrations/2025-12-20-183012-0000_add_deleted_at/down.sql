@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM config WHERE key = 'trash.retention_days';
+ALTER TABLE uploads DROP COLUMN deleted_at;
@@ -0,0 +1,5 @@
+-- Soft delete: trashed uploads keep their row until purged
+ALTER TABLE uploads ADD COLUMN deleted_at TIMESTAMP;
+
+-- Insert default trash retention (30 days)
+INSERT OR IGNORE INTO config (key, value) VALUES ('trash.retention_days', '30');
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE upload_tags;
+DROP TABLE album_uploads;
+DROP TABLE albums;
@@ -0,0 +1,24 @@
+-- Albums group uploads into named sets
+CREATE TABLE albums (
+    uuid BLOB PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL UNIQUE,
+    description TEXT,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+-- SQLite doesn't enforce the foreign keys here, the album and upload services remove rows of
+-- deleted albums and purged uploads themselves
+CREATE TABLE album_uploads (
+    album_uuid BLOB NOT NULL REFERENCES albums(uuid),
+    upload_uuid BLOB NOT NULL REFERENCES uploads(uuid),
+    PRIMARY KEY (album_uuid, upload_uuid)
+);
+
+-- Free-form tags, normalized to lowercase
+CREATE TABLE upload_tags (
+    upload_uuid BLOB NOT NULL REFERENCES uploads(uuid),
+    tag TEXT NOT NULL,
+    PRIMARY KEY (upload_uuid, tag)
+);
+
+CREATE INDEX upload_tags_tag ON upload_tags (tag);
@@ -0,0 +1,16 @@
+-- Named, ordered playlists for the screensaver
+CREATE TABLE playlists (
+    uuid BLOB PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+-- duration_seconds overrides the screensaver interval for a single item. SQLite doesn't enforce
+-- the foreign keys, items of deleted playlists and purged uploads are removed in code.
+CREATE TABLE playlist_items (
+    playlist_uuid BLOB NOT NULL REFERENCES playlists(uuid),
+    position INTEGER NOT NULL,
+    upload_uuid BLOB NOT NULL REFERENCES uploads(uuid),
+    duration_seconds INTEGER,
+    PRIMARY KEY (playlist_uuid, position)
+);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM config WHERE key = 'timezone';
+DROP TABLE schedule_rules;
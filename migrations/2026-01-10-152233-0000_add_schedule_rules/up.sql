@@ -0,0 +1,21 @@
+-- Time-based rules that override the slideshow with an upload or playlist
+CREATE TABLE schedule_rules (
+    uuid BLOB PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    -- Bitmask of weekdays the rule applies on, bit 0 is Monday
+    weekdays INTEGER NOT NULL DEFAULT 127,
+    -- Restrict the rule to one day of the month (1-31)
+    day_of_month INTEGER,
+    -- Local HH:MM times, an end before the start wraps past midnight
+    start_time TEXT NOT NULL,
+    end_time TEXT NOT NULL,
+    -- Not enforced by SQLite, rules are removed in code with their playlist or purged upload
+    upload_uuid BLOB REFERENCES uploads(uuid),
+    playlist_uuid BLOB REFERENCES playlists(uuid),
+    priority INTEGER NOT NULL DEFAULT 0,
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Timezone schedule rules are evaluated in
+INSERT OR IGNORE INTO config (key, value) VALUES ('timezone', 'UTC');
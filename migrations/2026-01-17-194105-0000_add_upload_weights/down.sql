@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE uploads DROP COLUMN favorite;
+ALTER TABLE uploads DROP COLUMN weight;
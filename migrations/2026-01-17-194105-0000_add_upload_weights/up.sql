@@ -0,0 +1,3 @@
+-- Relative weight used by the weighted slideshow mode, favorites get a boost on top
+ALTER TABLE uploads ADD COLUMN weight INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE uploads ADD COLUMN favorite BOOLEAN NOT NULL DEFAULT 0;
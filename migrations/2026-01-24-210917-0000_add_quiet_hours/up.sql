@@ -0,0 +1,11 @@
+-- Quiet hours are stored per weekday as HH:MM-HH:MM, empty means none
+INSERT OR IGNORE INTO config (key, value) VALUES ('quiet_hours.monday', '');
+INSERT OR IGNORE INTO config (key, value) VALUES ('quiet_hours.tuesday', '');
+INSERT OR IGNORE INTO config (key, value) VALUES ('quiet_hours.wednesday', '');
+INSERT OR IGNORE INTO config (key, value) VALUES ('quiet_hours.thursday', '');
+INSERT OR IGNORE INTO config (key, value) VALUES ('quiet_hours.friday', '');
+INSERT OR IGNORE INTO config (key, value) VALUES ('quiet_hours.saturday', '');
+INSERT OR IGNORE INTO config (key, value) VALUES ('quiet_hours.sunday', '');
+
+-- What the RGB display shows during quiet hours: blank or dim
+INSERT OR IGNORE INTO config (key, value) VALUES ('quiet_hours.frame', 'blank');
@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM config WHERE key LIKE 'screensaver.esp32.%';
@@ -0,0 +1,2 @@
+-- The monochrome Esp32 display runs its own slideshow with its own settings
+INSERT OR IGNORE INTO config (key, value) VALUES ('screensaver.esp32.interval', '120');
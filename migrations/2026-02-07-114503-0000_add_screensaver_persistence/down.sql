@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM config WHERE key IN (
+    'screensaver.paused',
+    'screensaver.current_upload',
+    'screensaver.esp32.paused',
+    'screensaver.esp32.current_upload'
+);
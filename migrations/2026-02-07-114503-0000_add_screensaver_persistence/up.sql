@@ -0,0 +1,5 @@
+-- Pause state and the upload on screen survive restarts
+INSERT OR IGNORE INTO config (key, value) VALUES ('screensaver.paused', 'false');
+INSERT OR IGNORE INTO config (key, value) VALUES ('screensaver.current_upload', '');
+INSERT OR IGNORE INTO config (key, value) VALUES ('screensaver.esp32.paused', 'false');
+INSERT OR IGNORE INTO config (key, value) VALUES ('screensaver.esp32.current_upload', '');
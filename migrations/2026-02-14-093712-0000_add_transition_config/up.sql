@@ -0,0 +1,3 @@
+-- Transition between slides on the RGB display: cut, crossfade, wipe or slide
+INSERT OR IGNORE INTO config (key, value) VALUES ('transition.type', 'cut');
+INSERT OR IGNORE INTO config (key, value) VALUES ('transition.duration_ms', '500');
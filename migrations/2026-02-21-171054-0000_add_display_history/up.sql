@@ -0,0 +1,14 @@
+-- Every slide a display showed, the one on screen has no end yet. Rows outlive their upload so
+-- the history stays complete after the trash is purged.
+CREATE TABLE display_history (
+    uuid BLOB PRIMARY KEY NOT NULL,
+    display TEXT NOT NULL,
+    upload_uuid BLOB NOT NULL,
+    started_at TIMESTAMP NOT NULL,
+    ended_at TIMESTAMP,
+    -- What put the slide on screen: timer, manual, new_upload or schedule
+    triggered_by TEXT NOT NULL
+);
+
+CREATE INDEX display_history_display_started_at ON display_history (display, started_at);
+CREATE INDEX display_history_upload_uuid ON display_history (upload_uuid);
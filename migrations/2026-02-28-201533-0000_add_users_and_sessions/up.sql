@@ -0,0 +1,20 @@
+-- Accounts that can log in, passwords are stored as argon2 hashes
+CREATE TABLE users (
+    uuid BLOB PRIMARY KEY NOT NULL,
+    username TEXT NOT NULL UNIQUE,
+    password_hash TEXT NOT NULL,
+    is_admin BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Cookie sessions of logged in users. SQLite doesn't enforce the foreign key, deleting a user
+-- removes their sessions in code.
+CREATE TABLE sessions (
+    -- Hex SHA-256 of the token in the session cookie
+    token_hash TEXT PRIMARY KEY NOT NULL,
+    user_uuid BLOB NOT NULL REFERENCES users(uuid),
+    created_at TIMESTAMP NOT NULL,
+    expires_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX sessions_user_uuid ON sessions (user_uuid);
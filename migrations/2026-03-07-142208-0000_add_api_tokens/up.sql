@@ -0,0 +1,15 @@
+-- Bearer tokens for scripts and devices. They act as their user, limited to their scopes. SQLite
+-- doesn't enforce the foreign key, deleting a user removes their tokens in code.
+CREATE TABLE api_tokens (
+    uuid BLOB PRIMARY KEY NOT NULL,
+    user_uuid BLOB NOT NULL REFERENCES users(uuid),
+    name TEXT NOT NULL,
+    -- Hex SHA-256 of the secret part of the token
+    token_hash TEXT NOT NULL,
+    -- Comma separated, e.g. upload:create,screensaver:control
+    scopes TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    last_used_at TIMESTAMP
+);
+
+CREATE INDEX api_tokens_user_uuid ON api_tokens (user_uuid);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX uploads_owner_uuid;
+ALTER TABLE uploads DROP COLUMN owner_uuid;
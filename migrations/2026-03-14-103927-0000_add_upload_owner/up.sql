@@ -0,0 +1,5 @@
+-- Who uploaded it, NULL for uploads from before accounts existed. Private uploads are only
+-- visible to their owner and admins.
+ALTER TABLE uploads ADD COLUMN owner_uuid BLOB REFERENCES users(uuid);
+
+CREATE INDEX uploads_owner_uuid ON uploads (owner_uuid);
@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM config WHERE key = 'moderation.enabled';
+ALTER TABLE users DROP COLUMN trusted;
+DROP INDEX uploads_status;
+ALTER TABLE uploads DROP COLUMN rejection_reason;
+ALTER TABLE uploads DROP COLUMN moderated_at;
+ALTER TABLE uploads DROP COLUMN moderated_by;
+ALTER TABLE uploads DROP COLUMN status;
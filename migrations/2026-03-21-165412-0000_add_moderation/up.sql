@@ -0,0 +1,13 @@
+-- Moderation state of an upload: pending, approved or rejected. Existing uploads are approved.
+ALTER TABLE uploads ADD COLUMN status TEXT NOT NULL DEFAULT 'approved';
+ALTER TABLE uploads ADD COLUMN moderated_by BLOB REFERENCES users(uuid);
+ALTER TABLE uploads ADD COLUMN moderated_at TIMESTAMP;
+ALTER TABLE uploads ADD COLUMN rejection_reason TEXT;
+
+CREATE INDEX uploads_status ON uploads (status);
+
+-- Trusted users skip the moderation queue
+ALTER TABLE users ADD COLUMN trusted BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- When enabled, uploads of users that aren't admins or trusted wait for approval
+INSERT OR IGNORE INTO config (key, value) VALUES ('moderation.enabled', 'false');
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE users DROP COLUMN storage_quota_mb;
+DELETE FROM config WHERE key LIKE 'limits.%';
@@ -0,0 +1,8 @@
+-- Uploads allowed per minute from one client IP and from one user, 0 turns the limit off
+INSERT OR IGNORE INTO config (key, value) VALUES ('limits.uploads_per_minute_ip', '30');
+INSERT OR IGNORE INTO config (key, value) VALUES ('limits.uploads_per_minute_user', '10');
+-- Storage each user may fill with uploads in megabytes, 0 turns the quota off
+INSERT OR IGNORE INTO config (key, value) VALUES ('limits.storage_quota_mb', '100');
+
+-- Overrides the default storage quota for one user
+ALTER TABLE users ADD COLUMN storage_quota_mb INTEGER;
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM config WHERE key = 'duplicates.policy';
+DROP INDEX uploads_content_hash;
+ALTER TABLE uploads DROP COLUMN content_hash;
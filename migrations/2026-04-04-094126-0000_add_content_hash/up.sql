@@ -0,0 +1,7 @@
+-- Hex SHA-256 of the upload data, filled in for existing uploads when the server starts
+ALTER TABLE uploads ADD COLUMN content_hash TEXT;
+
+CREATE INDEX uploads_content_hash ON uploads (content_hash);
+
+-- What to do with an upload identical to an existing one: reject, warn or link
+INSERT OR IGNORE INTO config (key, value) VALUES ('duplicates.policy', 'warn');
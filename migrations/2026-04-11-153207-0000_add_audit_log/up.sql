@@ -0,0 +1,23 @@
+-- Every change made through the APIs, who made it and what it looked like before and after.
+-- Rows outlive their actor and target so the log stays complete after deletes.
+CREATE TABLE audit_log (
+    uuid BLOB PRIMARY KEY NOT NULL,
+    actor_uuid BLOB,
+    -- Username at the time, empty for anonymous requests
+    actor_name TEXT,
+    client_ip TEXT,
+    -- Name of the mutation, e.g. updateUpload
+    action TEXT NOT NULL,
+    -- What was changed: upload, user, api_token, album, playlist, schedule_rule, display or config
+    target_type TEXT NOT NULL,
+    -- UUID, display name or config key of the target
+    target_id TEXT NOT NULL,
+    -- JSON snapshots of the relevant fields
+    old_value TEXT,
+    new_value TEXT,
+    created_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX audit_log_created_at ON audit_log (created_at);
+CREATE INDEX audit_log_actor_uuid ON audit_log (actor_uuid);
+CREATE INDEX audit_log_target ON audit_log (target_type, target_id);
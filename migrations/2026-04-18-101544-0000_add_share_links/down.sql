@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DELETE FROM config WHERE key = 'share_links.secret';
+DROP TABLE share_links;
@@ -0,0 +1,16 @@
+-- Links that show one upload to whoever holds them, private or not, until they expire or are
+-- revoked. The URL carries an HMAC signature so links can't be made up or altered.
+CREATE TABLE share_links (
+    uuid BLOB PRIMARY KEY NOT NULL,
+    upload_uuid BLOB NOT NULL REFERENCES uploads(uuid),
+    -- User who shared the upload
+    created_by BLOB,
+    expires_at TIMESTAMP NOT NULL,
+    revoked_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX share_links_upload_uuid ON share_links (upload_uuid);
+
+-- Key the links are signed with. Replacing it invalidates every link handed out so far.
+INSERT OR IGNORE INTO config (key, value) VALUES ('share_links.secret', lower(hex(randomblob(32))));
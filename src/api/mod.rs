@@ -1,24 +1,204 @@
 // use core::fmt;
 // use std::str::FromStr;
 
-use axum::http::HeaderMap;
+use axum::extract::{ConnectInfo, Path, Query};
+use axum::http::{header, HeaderMap, Uri};
 use axum::response::IntoResponse;
 use axum::response::Response;
-use axum::Json;
+use axum::routing::post;
 use axum::{http::StatusCode, routing::get, Router};
+use axum::{Extension, Json};
+use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
 // use serde::{de, Deserialize, Deserializer, Serialize};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use std::time::Instant;
+use uuid::Uuid;
 
 use crate::context::GraphQLContext;
+use crate::models::{ApiScope, AuditTargetType, DisplayFormat, Upload, UploadInput, User};
+use crate::svc::{
+    packed_to_png, upload_snapshot, ApiTokenSvc, AuditSvc, LimitError, SessionSvc, ShareParams,
+    ShareSvc, UploadSvc, UserSvc, ValidationError, LOGIN_ATTEMPTS_PER_MINUTE, SESSION_COOKIE,
+    SESSION_DAYS,
+};
+use crate::{get_env_typed, is_production};
 
 pub fn api_routes(_context: GraphQLContext) -> Router {
-    Router::new().route("/test", get(test))
+    let routes = Router::new()
+        .route("/login", post(login))
+        .route("/logout", post(logout))
+        .route("/me", get(me))
+        .route("/uploads", post(create_upload))
+        .route("/uploads/{upload_uuid}/image", get(upload_image))
+        .route("/displays/{display}/current", get(current_frame));
+    if is_production() {
+        routes
+    } else {
+        routes.route("/test", get(test))
+    }
 }
 
 pub async fn test(_headers: HeaderMap) -> Result<impl IntoResponse, AppError> {
     Ok("test")
 }
 
+/// Attach the user of the request's bearer token or session cookie to the context
+pub fn authenticate(context: &GraphQLContext, headers: &HeaderMap) -> GraphQLContext {
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    if let Some(token) = bearer {
+        return match ApiTokenSvc::authenticate(context, token.trim()) {
+            Ok(Some((user, scopes))) => context.with_token(user, scopes),
+            Ok(None) => context.with_user(None),
+            Err(e) => {
+                tracing::warn!("Could not look up API token: {:?}", e);
+                context.with_user(None)
+            }
+        };
+    }
+
+    let jar = CookieJar::from_headers(headers);
+    let user = jar.get(SESSION_COOKIE).and_then(|cookie| {
+        SessionSvc::user(context, cookie.value())
+            .map_err(|e| tracing::warn!("Could not look up session: {:?}", e))
+            .ok()
+            .flatten()
+    });
+    context.with_user(user)
+}
+
+/// Address of the client, taken from `X-Forwarded-For` when `TRUST_PROXY` says a reverse proxy
+/// sets it
+pub fn client_ip(headers: &HeaderMap, peer: SocketAddr) -> IpAddr {
+    if get_env_typed("TRUST_PROXY", false) {
+        if let Some(ip) = forwarded_ip(headers) {
+            return ip;
+        }
+    }
+    peer.ip()
+}
+
+/// The last `X-Forwarded-For` entry, the one the proxy appended. Entries before it come from the
+/// client and can be anything.
+fn forwarded_ip(headers: &HeaderMap) -> Option<IpAddr> {
+    headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .next_back()?
+        .to_str()
+        .ok()?
+        .rsplit(',')
+        .next()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Reject requests that aren't authenticated or whose token lacks `scope`
+fn authorize(context: &GraphQLContext, scope: ApiScope) -> Result<(), (StatusCode, String)> {
+    if context.user.is_none() {
+        return Err((StatusCode::UNAUTHORIZED, "Not logged in".to_owned()));
+    }
+    if !context.has_scope(scope) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            format!("API token lacks the {} scope", scope.as_str()),
+        ));
+    }
+    Ok(())
+}
+
+#[derive(Deserialize)]
+pub struct LoginRequest {
+    username: String,
+    password: String,
+}
+
+/// The logged in user as returned by the REST API
+#[derive(Serialize)]
+pub struct Me {
+    uuid: Uuid,
+    username: String,
+    is_admin: bool,
+}
+
+impl From<User> for Me {
+    fn from(user: User) -> Self {
+        Self {
+            uuid: user.uuid.into(),
+            username: user.username,
+            is_admin: user.is_admin,
+        }
+    }
+}
+
+/// Check the credentials and start a cookie session
+pub async fn login(
+    Extension(context): Extension<GraphQLContext>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    jar: CookieJar,
+    Json(request): Json<LoginRequest>,
+) -> Result<Response, AppError> {
+    let ip = client_ip(&headers, peer);
+    if let Err(LimitError::RateLimited { retry_after }) = context.rate_limiter.check(
+        &format!("login:{ip}"),
+        LOGIN_ATTEMPTS_PER_MINUTE,
+        Instant::now(),
+    ) {
+        let retry_after = retry_after.as_secs().max(1);
+        return Ok((
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, retry_after.to_string())],
+            format!("Too many login attempts, try again in {retry_after} seconds"),
+        )
+            .into_response());
+    }
+
+    let user = match UserSvc::authenticate(&context, &request.username, &request.password) {
+        Ok(user) => user,
+        Err(e) => return Ok((StatusCode::UNAUTHORIZED, e.to_string()).into_response()),
+    };
+    let token = SessionSvc::create(&context, &user)?;
+
+    // Expire the cookie along with the session, HTTPS only in production unless turned off
+    let cookie = Cookie::build((SESSION_COOKIE, token))
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .secure(get_env_typed("COOKIE_SECURE", is_production()))
+        .max_age(time::Duration::days(SESSION_DAYS));
+    Ok((jar.add(cookie), Json(Me::from(user))).into_response())
+}
+
+/// End the session of the request's cookie
+pub async fn logout(
+    Extension(context): Extension<GraphQLContext>,
+    jar: CookieJar,
+) -> Result<impl IntoResponse, AppError> {
+    if let Some(cookie) = jar.get(SESSION_COOKIE) {
+        SessionSvc::delete(&context, cookie.value())?;
+    }
+    Ok((
+        jar.remove(Cookie::build(SESSION_COOKIE).path("/")),
+        StatusCode::NO_CONTENT,
+    ))
+}
+
+pub async fn me(
+    Extension(context): Extension<GraphQLContext>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    match authenticate(&context, &headers).user {
+        Some(user) => Ok(Json(Me::from(user)).into_response()),
+        None => Ok((StatusCode::UNAUTHORIZED, "Not logged in").into_response()),
+    }
+}
+
 pub fn err_wrapper<T: Serialize>(result: anyhow::Result<T>) -> impl IntoResponse {
     Json(
         result
@@ -41,12 +221,132 @@ pub fn err_wrapper<T: Serialize>(result: anyhow::Result<T>) -> impl IntoResponse
 //     }
 // }
 
+/// Create an upload, the same as the `createUpload` mutation
+pub async fn create_upload(
+    Extension(context): Extension<GraphQLContext>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(upload): Json<UploadInput>,
+) -> Result<Response, AppError> {
+    let context = authenticate(&context, &headers).with_client_ip(client_ip(&headers, peer));
+    if let Err(rejection) = authorize(&context, ApiScope::UploadCreate) {
+        return Ok(rejection.into_response());
+    }
+
+    let upload = Upload::try_from(upload)?;
+    let created = UploadSvc::create(&context, &upload).await?;
+    AuditSvc::record(
+        &context,
+        "createUpload",
+        AuditTargetType::Upload,
+        created.uuid,
+        None,
+        Some(upload_snapshot(&created)),
+    );
+    // The duplicate policy may hand back an existing upload instead
+    let status = if created.uuid == upload.uuid {
+        StatusCode::CREATED
+    } else {
+        StatusCode::OK
+    };
+    let uuid: Uuid = created.uuid.into();
+    Ok((
+        status,
+        Json(serde_json::json!({ "uuid": uuid, "status": created.status })),
+    )
+        .into_response())
+}
+
+/// An upload as an image, JPEG for the RGB display and PNG for the Esp32. Private uploads are
+/// only served to their owner and admins.
+pub async fn upload_image(
+    Extension(context): Extension<GraphQLContext>,
+    headers: HeaderMap,
+    Path(upload_uuid): Path<Uuid>,
+    uri: Uri,
+) -> Result<Response, AppError> {
+    let context = authenticate(&context, &headers);
+    let upload = match Query::<ShareParams>::try_from_uri(&uri) {
+        // A share link shows the upload to anyone, private or not, while it is valid
+        Ok(Query(share)) => {
+            if ShareSvc::verify(&context, upload_uuid, &share).await? {
+                UploadSvc::get(&context, upload_uuid)
+                    .ok()
+                    .filter(|upload| upload.is_approved())
+            } else {
+                None
+            }
+        }
+        Err(_) => UploadSvc::get_visible(&context, upload_uuid).ok(),
+    };
+    let Some(upload) = upload else {
+        return Ok((StatusCode::NOT_FOUND, "Could not find upload").into_response());
+    };
+
+    if upload.display_format() == Some(DisplayFormat::RGB320x240) {
+        return Ok(([(header::CONTENT_TYPE, "image/jpeg")], upload.data).into_response());
+    }
+    let png = packed_to_png(upload.data).await;
+    Ok(([(header::CONTENT_TYPE, "image/png")], png).into_response())
+}
+
+/// The frame a display should show right now, for devices that pull instead of being pushed to
+pub async fn current_frame(
+    Extension(context): Extension<GraphQLContext>,
+    headers: HeaderMap,
+    Path(display): Path<String>,
+) -> Result<Response, AppError> {
+    let context = authenticate(&context, &headers);
+    if let Err(rejection) = authorize(&context, ApiScope::DevicePull) {
+        return Ok(rejection.into_response());
+    }
+
+    let Ok(display) = DisplayFormat::from_str(&display) else {
+        return Ok((StatusCode::NOT_FOUND, "Unknown display").into_response());
+    };
+    let Some(screensaver) = context.screensaver_for(display) else {
+        return Ok((
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Screensaver service not available",
+        )
+            .into_response());
+    };
+    match screensaver.device_frame().await? {
+        Some(frame) => {
+            Ok(([(header::CONTENT_TYPE, "application/octet-stream")], frame).into_response())
+        }
+        None => Ok(StatusCode::NO_CONTENT.into_response()),
+    }
+}
+
 // Make our own error that wraps `anyhow::Error`.
 pub struct AppError(anyhow::Error);
 
 // Tell axum how to convert `AppError` into a response.
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
+        match self.0.downcast_ref::<LimitError>() {
+            Some(LimitError::RateLimited { retry_after }) => {
+                let retry_after = retry_after.as_secs().max(1).to_string();
+                return (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    [(header::RETRY_AFTER, retry_after)],
+                    self.0.to_string(),
+                )
+                    .into_response();
+            }
+            Some(LimitError::QuotaExceeded { .. }) => {
+                return (StatusCode::TOO_MANY_REQUESTS, self.0.to_string()).into_response();
+            }
+            None => {}
+        }
+        if let Some(ValidationError(violations)) = self.0.downcast_ref::<ValidationError>() {
+            return (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(serde_json::json!({ "errors": violations })),
+            )
+                .into_response();
+        }
         (
             StatusCode::INTERNAL_SERVER_ERROR,
             format!("Something went wrong: {:?}", self.0),
@@ -65,3 +365,34 @@ where
         Self(err.into())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn it_takes_the_ip_the_proxy_appended() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-forwarded-for",
+            HeaderValue::from_static("6.6.6.6, 10.0.0.7"),
+        );
+        assert_eq!(forwarded_ip(&headers), "10.0.0.7".parse().ok());
+
+        // A second header added by the proxy wins over one the client sent
+        headers.append("x-forwarded-for", HeaderValue::from_static("10.0.0.8"));
+        assert_eq!(forwarded_ip(&headers), "10.0.0.8".parse().ok());
+    }
+
+    #[test]
+    fn it_ignores_unparseable_forwarded_ips() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(forwarded_ip(&headers), None);
+        headers.insert(
+            "x-forwarded-for",
+            HeaderValue::from_static("10.0.0.7, nope"),
+        );
+        assert_eq!(forwarded_ip(&headers), None);
+    }
+}
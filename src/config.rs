@@ -1,8 +1,14 @@
 use crate::context::GraphQLContext;
 use crate::db::get_conn;
+use crate::models::{
+    DisplayFormat, DuplicatePolicy, QuietFrame, QuietHours, SlideshowMode, Transition,
+};
 use crate::schema::config;
+use crate::svc::ALL_WEEKDAYS;
+use chrono_tz::Tz;
 use diesel::prelude::*;
 use std::str::FromStr;
+use uuid::Uuid;
 
 #[derive(Queryable, Insertable, Selectable, Debug)]
 #[diesel(table_name = config)]
@@ -59,11 +65,22 @@ impl ConfigSvc {
         Ok(())
     }
 
+    /// Key of a per-display screensaver setting. The RGB display keeps the original
+    /// `screensaver.*` keys, other displays get their own namespace.
+    fn screensaver_key(display: DisplayFormat, name: &str) -> String {
+        match display {
+            DisplayFormat::RGB320x240 => format!("screensaver.{name}"),
+            DisplayFormat::Esp32 => format!("screensaver.esp32.{name}"),
+        }
+    }
+
     /// Get screensaver interval in seconds, defaults to 120 seconds
     pub async fn get_screensaver_interval(
         ctx: &GraphQLContext,
+        display: DisplayFormat,
     ) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
-        let value = Self::get_config_value(ctx, "screensaver.interval", "120").await?;
+        let key = Self::screensaver_key(display, "interval");
+        let value = Self::get_config_value(ctx, &key, "120").await?;
         let seconds = u64::from_str(&value)?;
         Ok(seconds)
     }
@@ -71,10 +88,360 @@ impl ConfigSvc {
     /// Set screensaver interval in seconds
     pub async fn set_screensaver_interval(
         ctx: &GraphQLContext,
+        display: DisplayFormat,
         seconds: u64,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        Self::set_config_value(ctx, "screensaver.interval", &seconds.to_string()).await?;
+        let key = Self::screensaver_key(display, "interval");
+        Self::set_config_value(ctx, &key, &seconds.to_string()).await?;
+        Ok(())
+    }
+
+    /// Get trash retention in days, defaults to 30 days
+    pub async fn get_trash_retention_days(
+        ctx: &GraphQLContext,
+    ) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        let value = Self::get_config_value(ctx, "trash.retention_days", "30").await?;
+        let days = u64::from_str(&value)?;
+        Ok(days)
+    }
+
+    /// Set trash retention in days
+    pub async fn set_trash_retention_days(
+        ctx: &GraphQLContext,
+        days: u64,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Self::set_config_value(ctx, "trash.retention_days", &days.to_string()).await?;
+        Ok(())
+    }
+
+    /// Get an optional UUID value, an empty value means unset
+    async fn get_uuid_value(
+        ctx: &GraphQLContext,
+        key: &str,
+    ) -> Result<Option<Uuid>, Box<dyn std::error::Error + Send + Sync>> {
+        let value = Self::get_config_value(ctx, key, "").await?;
+        if value.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Uuid::parse_str(&value)?))
+    }
+
+    /// Set an optional UUID value, `None` is stored as an empty value
+    async fn set_uuid_value(
+        ctx: &GraphQLContext,
+        key: &str,
+        value: Option<Uuid>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let value = value.map(|value| value.to_string()).unwrap_or_default();
+        Self::set_config_value(ctx, key, &value).await?;
+        Ok(())
+    }
+
+    /// Get the album the screensaver is restricted to, if any
+    pub async fn get_screensaver_album(
+        ctx: &GraphQLContext,
+        display: DisplayFormat,
+    ) -> Result<Option<Uuid>, Box<dyn std::error::Error + Send + Sync>> {
+        Self::get_uuid_value(ctx, &Self::screensaver_key(display, "album")).await
+    }
+
+    /// Set the album the screensaver is restricted to, `None` clears the restriction
+    pub async fn set_screensaver_album(
+        ctx: &GraphQLContext,
+        display: DisplayFormat,
+        album: Option<Uuid>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Self::set_uuid_value(ctx, &Self::screensaver_key(display, "album"), album).await
+    }
+
+    /// Get the playlist the screensaver runs, if any
+    pub async fn get_screensaver_playlist(
+        ctx: &GraphQLContext,
+        display: DisplayFormat,
+    ) -> Result<Option<Uuid>, Box<dyn std::error::Error + Send + Sync>> {
+        Self::get_uuid_value(ctx, &Self::screensaver_key(display, "playlist")).await
+    }
+
+    /// Set the playlist the screensaver runs, `None` returns to the regular slideshow
+    pub async fn set_screensaver_playlist(
+        ctx: &GraphQLContext,
+        display: DisplayFormat,
+        playlist: Option<Uuid>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Self::set_uuid_value(ctx, &Self::screensaver_key(display, "playlist"), playlist).await
+    }
+
+    /// Get whether the screensaver was paused, defaults to running
+    pub async fn get_screensaver_paused(
+        ctx: &GraphQLContext,
+        display: DisplayFormat,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let key = Self::screensaver_key(display, "paused");
+        let value = Self::get_config_value(ctx, &key, "false").await?;
+        let paused = bool::from_str(&value)?;
+        Ok(paused)
+    }
+
+    /// Set whether the screensaver is paused
+    pub async fn set_screensaver_paused(
+        ctx: &GraphQLContext,
+        display: DisplayFormat,
+        paused: bool,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let key = Self::screensaver_key(display, "paused");
+        Self::set_config_value(ctx, &key, &paused.to_string()).await?;
+        Ok(())
+    }
+
+    /// Get the upload the screensaver last displayed, if any
+    pub async fn get_screensaver_current_upload(
+        ctx: &GraphQLContext,
+        display: DisplayFormat,
+    ) -> Result<Option<Uuid>, Box<dyn std::error::Error + Send + Sync>> {
+        Self::get_uuid_value(ctx, &Self::screensaver_key(display, "current_upload")).await
+    }
+
+    /// Set the upload the screensaver displays
+    pub async fn set_screensaver_current_upload(
+        ctx: &GraphQLContext,
+        display: DisplayFormat,
+        upload: Option<Uuid>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Self::set_uuid_value(ctx, &Self::screensaver_key(display, "current_upload"), upload).await
+    }
+
+    /// Get the timezone local times are evaluated in, defaults to UTC
+    pub async fn get_timezone(
+        ctx: &GraphQLContext,
+    ) -> Result<Tz, Box<dyn std::error::Error + Send + Sync>> {
+        let value = Self::get_config_value(ctx, "timezone", "UTC").await?;
+        let timezone = Tz::from_str(&value).map_err(|e| e.to_string())?;
+        Ok(timezone)
+    }
+
+    /// Set the timezone local times are evaluated in
+    pub async fn set_timezone(
+        ctx: &GraphQLContext,
+        timezone: Tz,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Self::set_config_value(ctx, "timezone", timezone.name()).await?;
+        Ok(())
+    }
+
+    /// Get the slideshow mode, defaults to sequential
+    pub async fn get_screensaver_mode(
+        ctx: &GraphQLContext,
+        display: DisplayFormat,
+    ) -> Result<SlideshowMode, Box<dyn std::error::Error + Send + Sync>> {
+        let key = Self::screensaver_key(display, "mode");
+        let value = Self::get_config_value(ctx, &key, "sequential").await?;
+        let mode = SlideshowMode::from_str(&value).map_err(|e| e.to_string())?;
+        Ok(mode)
+    }
+
+    /// Set the slideshow mode
+    pub async fn set_screensaver_mode(
+        ctx: &GraphQLContext,
+        display: DisplayFormat,
+        mode: SlideshowMode,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let key = Self::screensaver_key(display, "mode");
+        Self::set_config_value(ctx, &key, mode.as_str()).await?;
+        Ok(())
+    }
+
+    /// Get the quiet hours of every weekday that has them, stored per weekday as `HH:MM-HH:MM`
+    pub async fn get_quiet_hours(
+        ctx: &GraphQLContext,
+    ) -> Result<Vec<QuietHours>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut quiet_hours = Vec::new();
+        for weekday in ALL_WEEKDAYS {
+            let key = format!("quiet_hours.{}", weekday.as_str());
+            let value = Self::get_config_value(ctx, &key, "").await?;
+            if value.is_empty() {
+                continue;
+            }
+            let (start_time, end_time) = value
+                .split_once('-')
+                .ok_or_else(|| format!("Invalid quiet hours for {}: {}", weekday.as_str(), value))?;
+            quiet_hours.push(QuietHours {
+                weekday,
+                start_time: start_time.to_owned(),
+                end_time: end_time.to_owned(),
+            });
+        }
+        Ok(quiet_hours)
+    }
+
+    /// Replace the quiet hours, weekdays missing from `quiet_hours` get none
+    pub async fn set_quiet_hours(
+        ctx: &GraphQLContext,
+        quiet_hours: &[QuietHours],
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        for weekday in ALL_WEEKDAYS {
+            let key = format!("quiet_hours.{}", weekday.as_str());
+            let value = quiet_hours
+                .iter()
+                .find(|hours| hours.weekday == weekday)
+                .map(|hours| format!("{}-{}", hours.start_time, hours.end_time))
+                .unwrap_or_default();
+            Self::set_config_value(ctx, &key, &value).await?;
+        }
+        Ok(())
+    }
+
+    /// Get what the RGB display shows during quiet hours, defaults to blank
+    pub async fn get_quiet_frame(
+        ctx: &GraphQLContext,
+    ) -> Result<QuietFrame, Box<dyn std::error::Error + Send + Sync>> {
+        let value = Self::get_config_value(ctx, "quiet_hours.frame", "blank").await?;
+        let frame = QuietFrame::from_str(&value).map_err(|e| e.to_string())?;
+        Ok(frame)
+    }
+
+    /// Set what the RGB display shows during quiet hours
+    pub async fn set_quiet_frame(
+        ctx: &GraphQLContext,
+        frame: QuietFrame,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Self::set_config_value(ctx, "quiet_hours.frame", frame.as_str()).await?;
+        Ok(())
+    }
+
+    /// Get the transition between slides on the RGB display
+    pub async fn get_transition(
+        ctx: &GraphQLContext,
+    ) -> Result<Transition, Box<dyn std::error::Error + Send + Sync>> {
+        let value = Self::get_config_value(ctx, "transition.type", "cut").await?;
+        let transition = Transition::from_str(&value).map_err(|e| e.to_string())?;
+        Ok(transition)
+    }
+
+    /// Set the transition between slides on the RGB display
+    pub async fn set_transition(
+        ctx: &GraphQLContext,
+        transition: Transition,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Self::set_config_value(ctx, "transition.type", transition.as_str()).await?;
+        Ok(())
+    }
+
+    /// Get how long a transition takes in milliseconds
+    pub async fn get_transition_duration_ms(
+        ctx: &GraphQLContext,
+    ) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        let value = Self::get_config_value(ctx, "transition.duration_ms", "500").await?;
+        let duration = u64::from_str(&value)?;
+        Ok(duration)
+    }
+
+    /// Set how long a transition takes in milliseconds
+    pub async fn set_transition_duration_ms(
+        ctx: &GraphQLContext,
+        duration_ms: u64,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Self::set_config_value(ctx, "transition.duration_ms", &duration_ms.to_string()).await?;
+        Ok(())
+    }
+
+    /// Get whether new uploads wait for a moderator, defaults to off
+    pub async fn get_moderation_enabled(
+        ctx: &GraphQLContext,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let value = Self::get_config_value(ctx, "moderation.enabled", "false").await?;
+        let enabled = bool::from_str(&value)?;
+        Ok(enabled)
+    }
+
+    /// Set whether new uploads wait for a moderator
+    pub async fn set_moderation_enabled(
+        ctx: &GraphQLContext,
+        enabled: bool,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Self::set_config_value(ctx, "moderation.enabled", &enabled.to_string()).await?;
         Ok(())
     }
-}
 
+    /// Get how many uploads one client IP may make per minute, 0 means unlimited
+    pub async fn get_uploads_per_minute_ip(
+        ctx: &GraphQLContext,
+    ) -> Result<u32, Box<dyn std::error::Error + Send + Sync>> {
+        let value = Self::get_config_value(ctx, "limits.uploads_per_minute_ip", "30").await?;
+        let limit = u32::from_str(&value)?;
+        Ok(limit)
+    }
+
+    /// Set how many uploads one client IP may make per minute
+    pub async fn set_uploads_per_minute_ip(
+        ctx: &GraphQLContext,
+        limit: u32,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Self::set_config_value(ctx, "limits.uploads_per_minute_ip", &limit.to_string()).await?;
+        Ok(())
+    }
+
+    /// Get how many uploads one user may make per minute, 0 means unlimited
+    pub async fn get_uploads_per_minute_user(
+        ctx: &GraphQLContext,
+    ) -> Result<u32, Box<dyn std::error::Error + Send + Sync>> {
+        let value = Self::get_config_value(ctx, "limits.uploads_per_minute_user", "10").await?;
+        let limit = u32::from_str(&value)?;
+        Ok(limit)
+    }
+
+    /// Set how many uploads one user may make per minute
+    pub async fn set_uploads_per_minute_user(
+        ctx: &GraphQLContext,
+        limit: u32,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Self::set_config_value(ctx, "limits.uploads_per_minute_user", &limit.to_string()).await?;
+        Ok(())
+    }
+
+    /// Get the default storage quota per user in megabytes, 0 means unlimited
+    pub async fn get_storage_quota_mb(
+        ctx: &GraphQLContext,
+    ) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        let value = Self::get_config_value(ctx, "limits.storage_quota_mb", "100").await?;
+        let quota = u64::from_str(&value)?;
+        Ok(quota)
+    }
+
+    /// Set the default storage quota per user in megabytes
+    pub async fn set_storage_quota_mb(
+        ctx: &GraphQLContext,
+        quota_mb: u64,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Self::set_config_value(ctx, "limits.storage_quota_mb", &quota_mb.to_string()).await?;
+        Ok(())
+    }
+
+    /// Get what happens to uploads identical to an existing one
+    pub async fn get_duplicate_policy(
+        ctx: &GraphQLContext,
+    ) -> Result<DuplicatePolicy, Box<dyn std::error::Error + Send + Sync>> {
+        let value = Self::get_config_value(ctx, "duplicates.policy", "warn").await?;
+        let policy = DuplicatePolicy::from_str(&value).map_err(|e| e.to_string())?;
+        Ok(policy)
+    }
+
+    /// Set what happens to uploads identical to an existing one
+    pub async fn set_duplicate_policy(
+        ctx: &GraphQLContext,
+        policy: DuplicatePolicy,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Self::set_config_value(ctx, "duplicates.policy", policy.as_str()).await?;
+        Ok(())
+    }
+
+    /// Get the key share links are signed with, the migration creates a random one
+    pub async fn get_share_link_secret(
+        ctx: &GraphQLContext,
+    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let secret = Self::get_config_value(ctx, "share_links.secret", "").await?;
+        if secret.is_empty() {
+            return Err("No share link secret configured".into());
+        }
+        Ok(secret)
+    }
+}
@@ -1,11 +1,104 @@
 use super::db::SqlitePool;
-use crate::svc::ScreensaverSvc;
-use std::sync::Arc;
+use crate::{
+    models::{ApiScope, DisplayFormat, User},
+    svc::{EventBus, RateLimiter, ScreensaverSvc},
+};
+use std::{net::IpAddr, sync::Arc};
 
 #[derive(Clone)]
 pub struct GraphQLContext {
     pub pool: SqlitePool,
+    /// Slideshow of the RGB display
     pub screensaver: Option<Arc<ScreensaverSvc>>,
+    /// Slideshow of the monochrome Esp32 display
+    pub mono_screensaver: Option<Arc<ScreensaverSvc>>,
+    /// Live updates for GraphQL subscriptions
+    pub events: EventBus,
+    /// User of the request's session or API token, `None` for anonymous requests
+    pub user: Option<User>,
+    /// Scopes of the request's API token, `None` for sessions which aren't limited
+    pub scopes: Option<Vec<ApiScope>>,
+    /// Address the request came from, `None` for work the server does by itself
+    pub client_ip: Option<IpAddr>,
+    /// Recent uploads and logins per client IP and user, shared by all requests
+    pub rate_limiter: RateLimiter,
+}
+
+impl GraphQLContext {
+    /// The same context acting on behalf of `user`
+    pub fn with_user(&self, user: Option<User>) -> Self {
+        Self {
+            user,
+            scopes: None,
+            ..self.clone()
+        }
+    }
+
+    /// The same context acting on behalf of an API token's user, limited to its scopes
+    pub fn with_token(&self, user: User, scopes: Vec<ApiScope>) -> Self {
+        Self {
+            user: Some(user),
+            scopes: Some(scopes),
+            ..self.clone()
+        }
+    }
+
+    /// The same context for a request from `client_ip`
+    pub fn with_client_ip(&self, client_ip: IpAddr) -> Self {
+        Self {
+            client_ip: Some(client_ip),
+            ..self.clone()
+        }
+    }
+
+    /// Whether the request is authenticated and allowed to use `scope`
+    pub fn has_scope(&self, scope: ApiScope) -> bool {
+        self.user.is_some()
+            && self
+                .scopes
+                .as_ref()
+                .is_none_or(|scopes| scopes.contains(&scope))
+    }
+
+    /// The slideshow driving the given display
+    pub fn screensaver_for(&self, display: DisplayFormat) -> Option<&Arc<ScreensaverSvc>> {
+        match display {
+            DisplayFormat::RGB320x240 => self.screensaver.as_ref(),
+            DisplayFormat::Esp32 => self.mono_screensaver.as_ref(),
+        }
+    }
+
+    /// Every running slideshow, for changes that affect all displays
+    pub fn screensavers(&self) -> impl Iterator<Item = &Arc<ScreensaverSvc>> {
+        self.screensaver.iter().chain(self.mono_screensaver.iter())
+    }
+}
+
+#[cfg(test)]
+impl GraphQLContext {
+    /// An anonymous context on a fresh in-memory database without slideshows
+    pub fn in_memory() -> Self {
+        use diesel::r2d2::{ConnectionManager, Pool};
+        use diesel::SqliteConnection;
+
+        // A single connection, each one would open a database of its own
+        let pool = Pool::builder()
+            .max_size(1)
+            .build(ConnectionManager::<SqliteConnection>::new(":memory:"))
+            .expect("Could not open in-memory database");
+        crate::db::run_migrations(&mut pool.get().expect("Could not get db connection"))
+            .expect("Could not run migrations");
+        Self {
+            pool,
+            screensaver: None,
+            mono_screensaver: None,
+            events: EventBus::default(),
+            user: None,
+            scopes: None,
+            client_ip: None,
+            rate_limiter: RateLimiter::default(),
+        }
+    }
 }
 
 impl juniper::Context for GraphQLContext {}
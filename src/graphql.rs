@@ -1,19 +1,106 @@
-use juniper::{EmptySubscription, FieldError, FieldResult, GraphQLObject, RootNode};
-use tracing::error;
+use chrono::NaiveDateTime;
+use futures::Stream;
+use juniper::{graphql_value, FieldError, FieldResult, GraphQLObject, RootNode};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::pin::Pin;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{error, warn};
 use uuid::Uuid;
 
 use crate::{
+    config::ConfigSvc,
     context::GraphQLContext,
-    models::{Upload, UploadInput},
-    svc::UploadSvc,
+    models::{
+        Album, ApiScope, ApiToken, AuditEntry, AuditLogFilter, AuditTargetType, DeviceStatus,
+        DisplayFormat, DisplayHistory, DuplicatePolicy, Playlist, PlaylistItemInput, QuietFrame,
+        QuietHours, QuietHoursInput, ScheduleRule, ScheduleRuleInput, ScreensaverChange, ShareLink,
+        SlideshowMode, Transition, Upload, UploadInput, UploadUpdateInput, User, UserInput,
+    },
+    svc::{
+        api_token_snapshot, quiet_hours_snapshot, schedule_rule_snapshot, share_link_snapshot,
+        upload_snapshot, user_snapshot, validate_upload, verify_password, AlbumSvc, ApiTokenSvc,
+        AuditSvc, DuplicateSvc, Event, HistorySvc, LimitError, LimitSvc, ModerationSvc,
+        PlaylistSvc, QuietHoursSvc, ScheduleSvc, ScreensaverState, ShareSvc, TagSvc, TransitionSvc,
+        UploadSvc, UserSvc, ValidationError, BYTES_PER_MB,
+    },
 };
 
 #[derive(GraphQLObject)]
 pub struct ScreensaverStatus {
+    display: DisplayFormat,
     is_running: bool,
+    /// Upload on screen, next and previous images follow from it. Empty when it is private.
+    current_upload_uuid: Option<Uuid>,
     current_index: i32,
+    /// Upload shown by `showUpload` while the slideshow holds, and until when. Empty when it is
+    /// private.
+    pinned_upload_uuid: Option<Uuid>,
+    pinned_until: Option<NaiveDateTime>,
     upload_count: i32,
     interval_seconds: i32,
+    album_uuid: Option<Uuid>,
+    playlist_uuid: Option<Uuid>,
+    slide_duration_seconds: Option<i32>,
+    schedule_rule_uuid: Option<Uuid>,
+    mode: SlideshowMode,
+    /// Whether quiet hours are in effect right now
+    quiet: bool,
+    quiet_hours: Vec<QuietHours>,
+    quiet_frame: QuietFrame,
+    /// How the RGB display changes slides, falls back to a cut when the device is too slow
+    transition: Transition,
+    transition_duration_ms: i32,
+}
+
+/// How much screen time an upload gets in the current slideshow
+#[derive(GraphQLObject)]
+#[graphql(context = GraphQLContext)]
+pub struct SlideShare {
+    upload: Upload,
+    /// Weight the slide is picked with, including the favorite boost
+    weight: i32,
+    /// Expected fraction of screen time, between 0 and 1
+    share: f64,
+}
+
+/// How long an upload was on screen
+#[derive(GraphQLObject)]
+#[graphql(context = GraphQLContext)]
+pub struct DisplayTime {
+    /// Empty when the upload is private or not approved
+    upload_uuid: Option<Uuid>,
+    /// Empty once the upload was purged from the trash or when it is private
+    upload: Option<Upload>,
+    seconds: i32,
+}
+
+/// A new API token along with its secret
+#[derive(GraphQLObject)]
+#[graphql(context = GraphQLContext)]
+pub struct CreatedApiToken {
+    api_token: ApiToken,
+    /// Send as `Authorization: Bearer <token>`, it can't be retrieved again
+    token: String,
+}
+
+/// Upload limits every user is held to, 0 means no limit
+#[derive(GraphQLObject, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadLimits {
+    uploads_per_minute_ip: i32,
+    uploads_per_minute_user: i32,
+    /// Default storage quota per user, admins can set their own per user
+    storage_quota_mb: i32,
+}
+
+/// Uploads with identical data
+#[derive(GraphQLObject)]
+#[graphql(context = GraphQLContext)]
+pub struct DuplicateGroup {
+    content_hash: String,
+    /// Oldest first
+    uploads: Vec<Upload>,
 }
 
 pub struct Query;
@@ -22,27 +109,143 @@ pub struct Query;
 impl Query {
     // Uploads
     pub async fn get_upload(context: &GraphQLContext, upload_uuid: Uuid) -> FieldResult<Upload> {
-        graphql_translate_anyhow(UploadSvc::get(context, upload_uuid))
+        graphql_translate_anyhow(UploadSvc::get_visible(context, upload_uuid))
+    }
+    /// Uploads of the logged in user, including private ones
+    pub fn my_uploads(
+        context: &GraphQLContext,
+        limit: Option<i32>,
+        offset: Option<i32>,
+    ) -> FieldResult<Vec<Upload>> {
+        let user = require_user(context)?;
+        let limit = limit.unwrap_or(100);
+        let offset = offset.unwrap_or(0);
+        graphql_translate_anyhow(UploadSvc::list_owned(
+            context,
+            user.uuid.into(),
+            limit,
+            offset,
+        ))
     }
     pub fn list_uploads(
         context: &GraphQLContext,
         limit: Option<i32>,
         offset: Option<i32>,
+        tag: Option<String>,
+        album_uuid: Option<Uuid>,
+    ) -> FieldResult<Vec<Upload>> {
+        let limit = limit.unwrap_or(100);
+        let offset = offset.unwrap_or(0);
+        graphql_translate_anyhow(UploadSvc::list(
+            context,
+            limit,
+            offset,
+            tag.as_deref(),
+            album_uuid,
+        ))
+    }
+    /// The whole trash for admins, otherwise only the user's own uploads
+    pub fn trashed_uploads(
+        context: &GraphQLContext,
+        limit: Option<i32>,
+        offset: Option<i32>,
     ) -> FieldResult<Vec<Upload>> {
+        let user = require_user(context)?;
+        let owner = (!user.is_admin).then_some(user.uuid.into());
         let limit = limit.unwrap_or(100);
         let offset = offset.unwrap_or(0);
-        graphql_translate_anyhow(UploadSvc::list(context, limit, offset))
+        graphql_translate_anyhow(UploadSvc::list_trash(context, owner, limit, offset))
+    }
+
+    // Share links
+    /// Links to an upload, including expired and revoked ones (owner or admins only)
+    pub fn share_links(context: &GraphQLContext, upload_uuid: Uuid) -> FieldResult<Vec<ShareLink>> {
+        require_user(context)?;
+        graphql_translate_anyhow(UploadSvc::get_managed(context, upload_uuid))?;
+        graphql_translate_anyhow(ShareSvc::list_for_upload(context, upload_uuid))
+    }
+
+    // Albums and tags
+    pub fn get_album(context: &GraphQLContext, album_uuid: Uuid) -> FieldResult<Album> {
+        graphql_translate_anyhow(AlbumSvc::get(context, album_uuid))
+    }
+    pub fn list_albums(context: &GraphQLContext) -> FieldResult<Vec<Album>> {
+        graphql_translate_anyhow(AlbumSvc::list(context))
+    }
+    pub fn list_tags(context: &GraphQLContext) -> FieldResult<Vec<String>> {
+        graphql_translate_anyhow(TagSvc::list(context))
+    }
+
+    // Playlists
+    pub fn get_playlist(context: &GraphQLContext, playlist_uuid: Uuid) -> FieldResult<Playlist> {
+        graphql_translate_anyhow(PlaylistSvc::get(context, playlist_uuid))
+    }
+    pub fn list_playlists(context: &GraphQLContext) -> FieldResult<Vec<Playlist>> {
+        graphql_translate_anyhow(PlaylistSvc::list(context))
+    }
+
+    // Scheduling
+    pub fn list_schedule_rules(context: &GraphQLContext) -> FieldResult<Vec<ScheduleRule>> {
+        graphql_translate_anyhow(ScheduleSvc::list(context))
+    }
+    pub async fn active_schedule_rule(
+        context: &GraphQLContext,
+    ) -> FieldResult<Option<ScheduleRule>> {
+        graphql_translate_anyhow(ScheduleSvc::active_rule(context, chrono::Utc::now()).await)
+    }
+    pub async fn timezone(context: &GraphQLContext) -> FieldResult<String> {
+        let timezone = ConfigSvc::get_timezone(context)
+            .await
+            .map_err(|e| FieldError::new(e.to_string(), juniper::Value::Null))?;
+        Ok(timezone.name().to_owned())
     }
 
     // Screensaver status
-    pub async fn screensaver_status(context: &GraphQLContext) -> FieldResult<ScreensaverStatus> {
-        if let Some(screensaver) = &context.screensaver {
+    pub async fn screensaver_status(
+        context: &GraphQLContext,
+        display: Option<DisplayFormat>,
+    ) -> FieldResult<ScreensaverStatus> {
+        if let Some(screensaver) =
+            context.screensaver_for(display.unwrap_or(DisplayFormat::RGB320x240))
+        {
             let state = screensaver.get_state().await;
+            let quiet_hours = ConfigSvc::get_quiet_hours(context)
+                .await
+                .map_err(|e| FieldError::new(e.to_string(), juniper::Value::Null))?;
+            let quiet_frame = ConfigSvc::get_quiet_frame(context)
+                .await
+                .map_err(|e| FieldError::new(e.to_string(), juniper::Value::Null))?;
+            let transition = ConfigSvc::get_transition(context)
+                .await
+                .map_err(|e| FieldError::new(e.to_string(), juniper::Value::Null))?;
+            let transition_duration_ms = ConfigSvc::get_transition_duration_ms(context)
+                .await
+                .map_err(|e| FieldError::new(e.to_string(), juniper::Value::Null))?;
             Ok(ScreensaverStatus {
+                display: screensaver.display(),
                 is_running: state.is_running,
+                current_upload_uuid: visible_upload_uuid(
+                    context,
+                    state.current.map(|current| current.upload.into()),
+                ),
                 current_index: state.current_index as i32,
+                pinned_upload_uuid: visible_upload_uuid(
+                    context,
+                    state.pinned.map(|pinned| pinned.upload.into()),
+                ),
+                pinned_until: state.pinned.map(|pinned| pinned.until),
                 upload_count: state.upload_count as i32,
                 interval_seconds: state.interval_seconds as i32,
+                album_uuid: state.album,
+                playlist_uuid: state.playlist,
+                slide_duration_seconds: state.slide_duration_seconds.map(|s| s as i32),
+                schedule_rule_uuid: state.schedule.map(|rule| rule.uuid.into()),
+                mode: state.mode,
+                quiet: state.quiet,
+                quiet_hours,
+                quiet_frame,
+                transition,
+                transition_duration_ms: transition_duration_ms as i32,
             })
         } else {
             Err(FieldError::new(
@@ -51,24 +254,951 @@ impl Query {
             ))
         }
     }
+    pub fn device_statuses(context: &GraphQLContext) -> Vec<DeviceStatus> {
+        context.events.device_statuses()
+    }
+    pub async fn slideshow_shares(
+        context: &GraphQLContext,
+        display: Option<DisplayFormat>,
+    ) -> FieldResult<Vec<SlideShare>> {
+        if let Some(screensaver) =
+            context.screensaver_for(display.unwrap_or(DisplayFormat::RGB320x240))
+        {
+            let shares = graphql_translate_anyhow(screensaver.slide_shares().await)?;
+            Ok(shares
+                .into_iter()
+                .filter(|(slide, _, _)| slide.upload.visible_to(context.user.as_ref()))
+                .map(|(slide, weight, share)| SlideShare {
+                    upload: slide.upload,
+                    weight,
+                    share,
+                })
+                .collect())
+        } else {
+            Err(FieldError::new(
+                "Screensaver service not available",
+                juniper::Value::Null,
+            ))
+        }
+    }
+
+    // Accounts
+    /// The logged in user, empty for anonymous requests
+    pub fn me(context: &GraphQLContext) -> Option<User> {
+        context.user.clone()
+    }
+    pub fn users(context: &GraphQLContext) -> FieldResult<Vec<User>> {
+        require_admin(context)?;
+        graphql_translate_anyhow(UserSvc::list(context))
+    }
+    /// API tokens of the logged in user
+    pub fn api_tokens(context: &GraphQLContext) -> FieldResult<Vec<ApiToken>> {
+        let user = require_user(context)?;
+        graphql_translate_anyhow(ApiTokenSvc::list_for_user(context, user.uuid.into()))
+    }
+
+    // Moderation
+    /// Whether new uploads wait for a moderator
+    pub async fn moderation_enabled(context: &GraphQLContext) -> FieldResult<bool> {
+        ConfigSvc::get_moderation_enabled(context)
+            .await
+            .map_err(|e| FieldError::new(e.to_string(), juniper::Value::Null))
+    }
+    /// Uploads waiting for a moderator, oldest first
+    pub fn pending_uploads(
+        context: &GraphQLContext,
+        limit: Option<i32>,
+        offset: Option<i32>,
+    ) -> FieldResult<Vec<Upload>> {
+        require_admin(context)?;
+        let limit = limit.unwrap_or(100);
+        let offset = offset.unwrap_or(0);
+        graphql_translate_anyhow(ModerationSvc::list_pending(context, limit, offset))
+    }
+
+    // Duplicates
+    pub async fn duplicate_policy(context: &GraphQLContext) -> FieldResult<DuplicatePolicy> {
+        ConfigSvc::get_duplicate_policy(context)
+            .await
+            .map_err(|e| FieldError::new(e.to_string(), juniper::Value::Null))
+    }
+    /// Groups of identical uploads outside the trash, the largest first (admins only)
+    pub fn duplicate_groups(
+        context: &GraphQLContext,
+        limit: Option<i32>,
+        offset: Option<i32>,
+    ) -> FieldResult<Vec<DuplicateGroup>> {
+        require_admin(context)?;
+        let limit = limit.unwrap_or(100);
+        let offset = offset.unwrap_or(0);
+        let groups = graphql_translate_anyhow(DuplicateSvc::groups(context, limit, offset))?;
+        Ok(groups
+            .into_iter()
+            .map(|(content_hash, uploads)| DuplicateGroup {
+                content_hash,
+                uploads,
+            })
+            .collect())
+    }
+
+    // Limits
+    pub async fn upload_limits(context: &GraphQLContext) -> FieldResult<UploadLimits> {
+        load_upload_limits(context).await
+    }
+
+    // Audit log
+    /// Changes made through the APIs, newest first (admins only)
+    pub fn audit_log(
+        context: &GraphQLContext,
+        filter: Option<AuditLogFilter>,
+        limit: Option<i32>,
+        offset: Option<i32>,
+    ) -> FieldResult<Vec<AuditEntry>> {
+        require_admin(context)?;
+        let filter = filter.unwrap_or_default();
+        let limit = limit.unwrap_or(100);
+        let offset = offset.unwrap_or(0);
+        graphql_translate_anyhow(AuditSvc::list(context, &filter, limit, offset))
+    }
+
+    // Display history
+    pub fn display_history(
+        context: &GraphQLContext,
+        display: Option<DisplayFormat>,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+        limit: Option<i32>,
+        offset: Option<i32>,
+    ) -> FieldResult<Vec<DisplayHistory>> {
+        let limit = limit.unwrap_or(100);
+        let offset = offset.unwrap_or(0);
+        graphql_translate_anyhow(HistorySvc::list(context, display, from, to, limit, offset))
+    }
+    pub fn displayed_at(
+        context: &GraphQLContext,
+        display: DisplayFormat,
+        at: NaiveDateTime,
+    ) -> FieldResult<Option<DisplayHistory>> {
+        graphql_translate_anyhow(HistorySvc::at(context, display, at))
+    }
+    pub fn display_times(
+        context: &GraphQLContext,
+        display: Option<DisplayFormat>,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+    ) -> FieldResult<Vec<DisplayTime>> {
+        let totals =
+            graphql_translate_anyhow(HistorySvc::display_times(context, display, from, to))?;
+        Ok(totals
+            .into_iter()
+            .map(|(upload, seconds)| {
+                let (upload_uuid, upload) = HistorySvc::upload(context, upload);
+                DisplayTime {
+                    upload_uuid,
+                    upload,
+                    seconds: seconds.try_into().unwrap_or(i32::MAX),
+                }
+            })
+            .collect())
+    }
 }
 
 pub struct Mutation;
 
 #[juniper::graphql_object(context = GraphQLContext)]
 impl Mutation {
+    // Accounts
+    pub fn create_user(context: &GraphQLContext, user: UserInput) -> FieldResult<User> {
+        require_admin(context)?;
+        let user = graphql_translate_anyhow(UserSvc::create(context, user))?;
+        AuditSvc::record(
+            context,
+            "createUser",
+            AuditTargetType::User,
+            user.uuid,
+            None,
+            Some(user_snapshot(&user)),
+        );
+        Ok(user)
+    }
+    pub fn delete_user(context: &GraphQLContext, user_uuid: Uuid) -> FieldResult<bool> {
+        let admin = require_admin(context)?;
+        if Uuid::from(admin.uuid) == user_uuid {
+            return Err(FieldError::new(
+                "You can't delete your own account",
+                juniper::Value::Null,
+            ));
+        }
+        let user = graphql_translate_anyhow(UserSvc::get(context, user_uuid))?;
+        graphql_translate_anyhow(UserSvc::delete(context, user_uuid))?;
+        AuditSvc::record(
+            context,
+            "deleteUser",
+            AuditTargetType::User,
+            user_uuid,
+            Some(user_snapshot(&user)),
+            None,
+        );
+        Ok(true)
+    }
+    /// Change your own password, this logs out all of your sessions
+    pub fn change_password(
+        context: &GraphQLContext,
+        current_password: String,
+        new_password: String,
+    ) -> FieldResult<bool> {
+        let user = require_user(context)?;
+        if !verify_password(&current_password, &user.password_hash) {
+            return Err(FieldError::new(
+                "Current password is incorrect",
+                juniper::Value::Null,
+            ));
+        }
+        graphql_translate_anyhow(UserSvc::set_password(
+            context,
+            user.uuid.into(),
+            &new_password,
+        ))?;
+        AuditSvc::record(
+            context,
+            "changePassword",
+            AuditTargetType::User,
+            user.uuid,
+            None,
+            None,
+        );
+        Ok(true)
+    }
+
+    /// Create an API token. The returned token string is shown only once.
+    pub fn create_api_token(
+        context: &GraphQLContext,
+        name: String,
+        scopes: Vec<ApiScope>,
+    ) -> FieldResult<CreatedApiToken> {
+        let user = require_user(context)?;
+        let (api_token, token) =
+            graphql_translate_anyhow(ApiTokenSvc::create(context, user, &name, &scopes))?;
+        AuditSvc::record(
+            context,
+            "createApiToken",
+            AuditTargetType::ApiToken,
+            api_token.uuid,
+            None,
+            Some(api_token_snapshot(&api_token)),
+        );
+        Ok(CreatedApiToken { api_token, token })
+    }
+    /// Revoke one of your API tokens, admins can revoke anyone's
+    pub fn revoke_api_token(context: &GraphQLContext, token_uuid: Uuid) -> FieldResult<bool> {
+        let user = require_user(context)?;
+        let api_token = graphql_translate_anyhow(ApiTokenSvc::get(context, token_uuid))?;
+        if api_token.user_uuid != user.uuid && !user.is_admin {
+            return Err(FieldError::new(
+                "Could not find API token",
+                juniper::Value::Null,
+            ));
+        }
+        graphql_translate_anyhow(ApiTokenSvc::delete(context, token_uuid))?;
+        AuditSvc::record(
+            context,
+            "revokeApiToken",
+            AuditTargetType::ApiToken,
+            token_uuid,
+            Some(api_token_snapshot(&api_token)),
+            None,
+        );
+        Ok(true)
+    }
+
     // Uploads
     pub async fn create_upload(
         context: &GraphQLContext,
         upload: UploadInput,
     ) -> FieldResult<Upload> {
-        graphql_translate_anyhow(UploadSvc::create(context, &upload.into()).await)
+        require_scope(context, ApiScope::UploadCreate)?;
+        let upload =
+            graphql_translate_anyhow(Upload::try_from(upload).map_err(anyhow::Error::from))?;
+        let created = graphql_translate_anyhow(UploadSvc::create(context, &upload).await)?;
+        AuditSvc::record(
+            context,
+            "createUpload",
+            AuditTargetType::Upload,
+            created.uuid,
+            None,
+            Some(upload_snapshot(&created)),
+        );
+        Ok(created)
+    }
+    pub async fn update_upload(
+        context: &GraphQLContext,
+        upload_uuid: Uuid,
+        upload: UploadUpdateInput,
+    ) -> FieldResult<Upload> {
+        require_user(context)?;
+        let mut existing = graphql_translate_anyhow(UploadSvc::get_managed(context, upload_uuid))?;
+        let old_value = upload_snapshot(&existing);
+        upload.apply_to(&mut existing);
+        // A different display may not suit the data
+        graphql_translate_anyhow(validate_upload(&existing).map_err(anyhow::Error::from))?;
+        let updated = graphql_translate_anyhow(UploadSvc::update(context, &existing).await)?;
+        AuditSvc::record(
+            context,
+            "updateUpload",
+            AuditTargetType::Upload,
+            upload_uuid,
+            Some(old_value),
+            Some(upload_snapshot(&updated)),
+        );
+        Ok(updated)
+    }
+    pub async fn delete_upload(context: &GraphQLContext, upload_uuid: Uuid) -> FieldResult<bool> {
+        require_user(context)?;
+        let existing = graphql_translate_anyhow(UploadSvc::get_managed(context, upload_uuid))?;
+        graphql_translate_anyhow(UploadSvc::delete(context, upload_uuid).await)?;
+        AuditSvc::record(
+            context,
+            "deleteUpload",
+            AuditTargetType::Upload,
+            upload_uuid,
+            Some(upload_snapshot(&existing)),
+            None,
+        );
+        Ok(true)
+    }
+    pub fn set_upload_weight(
+        context: &GraphQLContext,
+        upload_uuid: Uuid,
+        weight: i32,
+    ) -> FieldResult<Upload> {
+        require_user(context)?;
+        let existing = graphql_translate_anyhow(UploadSvc::get_managed(context, upload_uuid))?;
+        let updated =
+            graphql_translate_anyhow(UploadSvc::set_weight(context, upload_uuid, weight))?;
+        AuditSvc::record(
+            context,
+            "setUploadWeight",
+            AuditTargetType::Upload,
+            upload_uuid,
+            Some(json!({ "weight": existing.weight })),
+            Some(json!({ "weight": updated.weight })),
+        );
+        Ok(updated)
+    }
+    pub fn set_upload_favorite(
+        context: &GraphQLContext,
+        upload_uuid: Uuid,
+        favorite: bool,
+    ) -> FieldResult<Upload> {
+        require_user(context)?;
+        let existing = graphql_translate_anyhow(UploadSvc::get_managed(context, upload_uuid))?;
+        let updated =
+            graphql_translate_anyhow(UploadSvc::set_favorite(context, upload_uuid, favorite))?;
+        AuditSvc::record(
+            context,
+            "setUploadFavorite",
+            AuditTargetType::Upload,
+            upload_uuid,
+            Some(json!({ "favorite": existing.favorite })),
+            Some(json!({ "favorite": updated.favorite })),
+        );
+        Ok(updated)
+    }
+    pub async fn restore_upload(
+        context: &GraphQLContext,
+        upload_uuid: Uuid,
+    ) -> FieldResult<Upload> {
+        let user = require_user(context)?;
+        let trashed = graphql_translate_anyhow(UploadSvc::get_trashed(context, upload_uuid))?;
+        if !trashed.manageable_by(Some(user)) {
+            return Err(FieldError::new(
+                "Only the owner of an upload or an admin can restore it",
+                juniper::Value::Null,
+            ));
+        }
+        let restored = graphql_translate_anyhow(UploadSvc::restore(context, upload_uuid).await)?;
+        AuditSvc::record(
+            context,
+            "restoreUpload",
+            AuditTargetType::Upload,
+            upload_uuid,
+            None,
+            Some(upload_snapshot(&restored)),
+        );
+        Ok(restored)
+    }
+    pub async fn set_trash_retention_days(
+        context: &GraphQLContext,
+        days: i32,
+    ) -> FieldResult<bool> {
+        require_admin(context)?;
+        if days <= 0 {
+            return Err(FieldError::new(
+                "Retention must be positive",
+                juniper::Value::Null,
+            ));
+        }
+        let old_days = ConfigSvc::get_trash_retention_days(context)
+            .await
+            .map_err(|e| FieldError::new(e.to_string(), juniper::Value::Null))?;
+        ConfigSvc::set_trash_retention_days(context, days as u64)
+            .await
+            .map_err(|e| FieldError::new(e.to_string(), juniper::Value::Null))?;
+        AuditSvc::record(
+            context,
+            "setTrashRetentionDays",
+            AuditTargetType::Config,
+            "trash.retention_days",
+            Some(json!(old_days)),
+            Some(json!(days)),
+        );
+        Ok(true)
+    }
+
+    // Share links
+    /// Let anyone with the returned link see the upload for `expiresIn` seconds, up to 30 days
+    pub fn create_share_link(
+        context: &GraphQLContext,
+        upload_uuid: Uuid,
+        expires_in: i32,
+    ) -> FieldResult<ShareLink> {
+        let user = require_user(context)?;
+        let upload = graphql_translate_anyhow(UploadSvc::get_managed(context, upload_uuid))?;
+        let link = graphql_translate_anyhow(ShareSvc::create(context, &upload, user, expires_in))?;
+        AuditSvc::record(
+            context,
+            "createShareLink",
+            AuditTargetType::Upload,
+            upload_uuid,
+            None,
+            Some(share_link_snapshot(&link)),
+        );
+        Ok(link)
+    }
+    /// Stop a share link from working, it stays listed as revoked
+    pub fn revoke_share_link(context: &GraphQLContext, link_uuid: Uuid) -> FieldResult<ShareLink> {
+        require_user(context)?;
+        let link = graphql_translate_anyhow(ShareSvc::get(context, link_uuid))?;
+        graphql_translate_anyhow(UploadSvc::get_managed(context, link.upload_uuid.into()))?;
+        let revoked = graphql_translate_anyhow(ShareSvc::revoke(context, link_uuid))?;
+        AuditSvc::record(
+            context,
+            "revokeShareLink",
+            AuditTargetType::Upload,
+            Uuid::from(link.upload_uuid),
+            Some(share_link_snapshot(&link)),
+            Some(share_link_snapshot(&revoked)),
+        );
+        Ok(revoked)
+    }
+
+    // Moderation
+    /// Make uploads of users that aren't admins or trusted wait for approval
+    pub async fn set_moderation_enabled(
+        context: &GraphQLContext,
+        enabled: bool,
+    ) -> FieldResult<bool> {
+        require_admin(context)?;
+        let was_enabled = ConfigSvc::get_moderation_enabled(context)
+            .await
+            .map_err(|e| FieldError::new(e.to_string(), juniper::Value::Null))?;
+        ConfigSvc::set_moderation_enabled(context, enabled)
+            .await
+            .map_err(|e| FieldError::new(e.to_string(), juniper::Value::Null))?;
+        AuditSvc::record(
+            context,
+            "setModerationEnabled",
+            AuditTargetType::Config,
+            "moderation.enabled",
+            Some(json!(was_enabled)),
+            Some(json!(enabled)),
+        );
+        Ok(true)
+    }
+    /// Let a pending or rejected upload through and show it right away
+    pub async fn approve_upload(
+        context: &GraphQLContext,
+        upload_uuid: Uuid,
+    ) -> FieldResult<Upload> {
+        require_admin(context)?;
+        let existing = graphql_translate_anyhow(UploadSvc::get(context, upload_uuid))?;
+        let approved =
+            graphql_translate_anyhow(ModerationSvc::approve(context, upload_uuid).await)?;
+        AuditSvc::record(
+            context,
+            "approveUpload",
+            AuditTargetType::Upload,
+            upload_uuid,
+            Some(json!({ "status": existing.status })),
+            Some(json!({ "status": approved.status })),
+        );
+        Ok(approved)
+    }
+    pub fn reject_upload(
+        context: &GraphQLContext,
+        upload_uuid: Uuid,
+        reason: Option<String>,
+    ) -> FieldResult<Upload> {
+        require_admin(context)?;
+        let existing = graphql_translate_anyhow(UploadSvc::get(context, upload_uuid))?;
+        let rejected =
+            graphql_translate_anyhow(ModerationSvc::reject(context, upload_uuid, reason))?;
+        AuditSvc::record(
+            context,
+            "rejectUpload",
+            AuditTargetType::Upload,
+            upload_uuid,
+            Some(json!({ "status": existing.status })),
+            Some(json!({
+                "status": rejected.status,
+                "rejectionReason": rejected.rejection_reason,
+            })),
+        );
+        Ok(rejected)
+    }
+    /// Let a user's uploads skip the moderation queue
+    pub fn set_user_trusted(
+        context: &GraphQLContext,
+        user_uuid: Uuid,
+        trusted: bool,
+    ) -> FieldResult<User> {
+        require_admin(context)?;
+        let existing = graphql_translate_anyhow(UserSvc::get(context, user_uuid))?;
+        graphql_translate_anyhow(ModerationSvc::set_trusted(context, user_uuid, trusted))?;
+        let user = graphql_translate_anyhow(UserSvc::get(context, user_uuid))?;
+        AuditSvc::record(
+            context,
+            "setUserTrusted",
+            AuditTargetType::User,
+            user_uuid,
+            Some(json!({ "trusted": existing.trusted })),
+            Some(json!({ "trusted": user.trusted })),
+        );
+        Ok(user)
+    }
+
+    // Duplicates
+    pub async fn set_duplicate_policy(
+        context: &GraphQLContext,
+        policy: DuplicatePolicy,
+    ) -> FieldResult<bool> {
+        require_admin(context)?;
+        let old_policy = ConfigSvc::get_duplicate_policy(context)
+            .await
+            .map_err(|e| FieldError::new(e.to_string(), juniper::Value::Null))?;
+        ConfigSvc::set_duplicate_policy(context, policy)
+            .await
+            .map_err(|e| FieldError::new(e.to_string(), juniper::Value::Null))?;
+        AuditSvc::record(
+            context,
+            "setDuplicatePolicy",
+            AuditTargetType::Config,
+            "duplicates.policy",
+            Some(json!(old_policy.as_str())),
+            Some(json!(policy.as_str())),
+        );
+        Ok(true)
+    }
+
+    // Limits
+    /// Change whichever of the limits are given, 0 turns a limit off
+    pub async fn set_upload_limits(
+        context: &GraphQLContext,
+        uploads_per_minute_ip: Option<i32>,
+        uploads_per_minute_user: Option<i32>,
+        storage_quota_mb: Option<i32>,
+    ) -> FieldResult<bool> {
+        require_admin(context)?;
+        let old_limits = load_upload_limits(context).await?;
+        graphql_translate_anyhow(
+            LimitSvc::set(
+                context,
+                uploads_per_minute_ip,
+                uploads_per_minute_user,
+                storage_quota_mb,
+            )
+            .await,
+        )?;
+        let new_limits = load_upload_limits(context).await?;
+        AuditSvc::record(
+            context,
+            "setUploadLimits",
+            AuditTargetType::Config,
+            "limits",
+            Some(json!(old_limits)),
+            Some(json!(new_limits)),
+        );
+        Ok(true)
+    }
+    /// Give a user their own storage quota, without `quotaMb` the default applies again
+    pub fn set_user_storage_quota(
+        context: &GraphQLContext,
+        user_uuid: Uuid,
+        quota_mb: Option<i32>,
+    ) -> FieldResult<User> {
+        require_admin(context)?;
+        let existing = graphql_translate_anyhow(UserSvc::get(context, user_uuid))?;
+        graphql_translate_anyhow(LimitSvc::set_user_quota(context, user_uuid, quota_mb))?;
+        let user = graphql_translate_anyhow(UserSvc::get(context, user_uuid))?;
+        AuditSvc::record(
+            context,
+            "setUserStorageQuota",
+            AuditTargetType::User,
+            user_uuid,
+            Some(json!({ "storageQuotaMb": existing.storage_quota_mb })),
+            Some(json!({ "storageQuotaMb": user.storage_quota_mb })),
+        );
+        Ok(user)
+    }
+
+    // Albums and tags
+    pub fn create_album(
+        context: &GraphQLContext,
+        name: String,
+        description: Option<String>,
+    ) -> FieldResult<Album> {
+        require_admin(context)?;
+        let album = graphql_translate_anyhow(AlbumSvc::create(context, &name, description))?;
+        AuditSvc::record(
+            context,
+            "createAlbum",
+            AuditTargetType::Album,
+            album.uuid,
+            None,
+            Some(json!({ "name": album.name, "description": album.description })),
+        );
+        Ok(album)
+    }
+    pub async fn delete_album(context: &GraphQLContext, album_uuid: Uuid) -> FieldResult<bool> {
+        require_admin(context)?;
+        let album = graphql_translate_anyhow(AlbumSvc::get(context, album_uuid))?;
+        graphql_translate_anyhow(AlbumSvc::delete(context, album_uuid).await)?;
+        AuditSvc::record(
+            context,
+            "deleteAlbum",
+            AuditTargetType::Album,
+            album_uuid,
+            Some(json!({ "name": album.name, "description": album.description })),
+            None,
+        );
+        Ok(true)
+    }
+    pub fn add_uploads_to_album(
+        context: &GraphQLContext,
+        album_uuid: Uuid,
+        upload_uuids: Vec<Uuid>,
+    ) -> FieldResult<Album> {
+        require_admin(context)?;
+        let album =
+            graphql_translate_anyhow(AlbumSvc::add_uploads(context, album_uuid, &upload_uuids))?;
+        AuditSvc::record(
+            context,
+            "addUploadsToAlbum",
+            AuditTargetType::Album,
+            album_uuid,
+            None,
+            Some(json!({ "uploadUuids": upload_uuids })),
+        );
+        Ok(album)
+    }
+    pub fn remove_upload_from_album(
+        context: &GraphQLContext,
+        album_uuid: Uuid,
+        upload_uuid: Uuid,
+    ) -> FieldResult<Album> {
+        require_admin(context)?;
+        let album =
+            graphql_translate_anyhow(AlbumSvc::remove_upload(context, album_uuid, upload_uuid))?;
+        AuditSvc::record(
+            context,
+            "removeUploadFromAlbum",
+            AuditTargetType::Album,
+            album_uuid,
+            Some(json!({ "uploadUuids": [upload_uuid] })),
+            None,
+        );
+        Ok(album)
+    }
+    pub fn tag_upload(
+        context: &GraphQLContext,
+        upload_uuid: Uuid,
+        tags: Vec<String>,
+    ) -> FieldResult<Upload> {
+        require_user(context)?;
+        let existing = graphql_translate_anyhow(UploadSvc::get_managed(context, upload_uuid))?;
+        let old_tags = graphql_translate_anyhow(TagSvc::list_for_upload(context, existing.uuid))?;
+        graphql_translate_anyhow(TagSvc::tag_upload(context, upload_uuid, &tags))?;
+        let new_tags = graphql_translate_anyhow(TagSvc::list_for_upload(context, existing.uuid))?;
+        AuditSvc::record(
+            context,
+            "tagUpload",
+            AuditTargetType::Upload,
+            upload_uuid,
+            Some(json!({ "tags": old_tags })),
+            Some(json!({ "tags": new_tags })),
+        );
+        graphql_translate_anyhow(UploadSvc::get(context, upload_uuid))
+    }
+    pub fn untag_upload(
+        context: &GraphQLContext,
+        upload_uuid: Uuid,
+        tag: String,
+    ) -> FieldResult<Upload> {
+        require_user(context)?;
+        let existing = graphql_translate_anyhow(UploadSvc::get_managed(context, upload_uuid))?;
+        let old_tags = graphql_translate_anyhow(TagSvc::list_for_upload(context, existing.uuid))?;
+        graphql_translate_anyhow(TagSvc::untag_upload(context, upload_uuid, &tag))?;
+        let new_tags = graphql_translate_anyhow(TagSvc::list_for_upload(context, existing.uuid))?;
+        AuditSvc::record(
+            context,
+            "untagUpload",
+            AuditTargetType::Upload,
+            upload_uuid,
+            Some(json!({ "tags": old_tags })),
+            Some(json!({ "tags": new_tags })),
+        );
+        graphql_translate_anyhow(UploadSvc::get(context, upload_uuid))
+    }
+
+    // Playlists
+    pub fn create_playlist(
+        context: &GraphQLContext,
+        name: String,
+        items: Option<Vec<PlaylistItemInput>>,
+    ) -> FieldResult<Playlist> {
+        require_admin(context)?;
+        let items = items.unwrap_or_default();
+        let playlist = graphql_translate_anyhow(PlaylistSvc::create(context, &name, &items))?;
+        AuditSvc::record(
+            context,
+            "createPlaylist",
+            AuditTargetType::Playlist,
+            playlist.uuid,
+            None,
+            Some(playlist_snapshot(context, &playlist)?),
+        );
+        Ok(playlist)
+    }
+    pub fn rename_playlist(
+        context: &GraphQLContext,
+        playlist_uuid: Uuid,
+        name: String,
+    ) -> FieldResult<Playlist> {
+        require_admin(context)?;
+        let existing = graphql_translate_anyhow(PlaylistSvc::get(context, playlist_uuid))?;
+        let playlist =
+            graphql_translate_anyhow(PlaylistSvc::rename(context, playlist_uuid, &name))?;
+        AuditSvc::record(
+            context,
+            "renamePlaylist",
+            AuditTargetType::Playlist,
+            playlist_uuid,
+            Some(json!({ "name": existing.name })),
+            Some(json!({ "name": playlist.name })),
+        );
+        Ok(playlist)
+    }
+    pub fn set_playlist_items(
+        context: &GraphQLContext,
+        playlist_uuid: Uuid,
+        items: Vec<PlaylistItemInput>,
+    ) -> FieldResult<Playlist> {
+        require_admin(context)?;
+        let existing = graphql_translate_anyhow(PlaylistSvc::get(context, playlist_uuid))?;
+        let old_value = playlist_snapshot(context, &existing)?;
+        let playlist =
+            graphql_translate_anyhow(PlaylistSvc::set_items(context, playlist_uuid, &items))?;
+        AuditSvc::record(
+            context,
+            "setPlaylistItems",
+            AuditTargetType::Playlist,
+            playlist_uuid,
+            Some(old_value),
+            Some(playlist_snapshot(context, &playlist)?),
+        );
+        Ok(playlist)
+    }
+    pub async fn delete_playlist(
+        context: &GraphQLContext,
+        playlist_uuid: Uuid,
+    ) -> FieldResult<bool> {
+        require_admin(context)?;
+        let existing = graphql_translate_anyhow(PlaylistSvc::get(context, playlist_uuid))?;
+        let old_value = playlist_snapshot(context, &existing)?;
+        graphql_translate_anyhow(PlaylistSvc::delete(context, playlist_uuid).await)?;
+        AuditSvc::record(
+            context,
+            "deletePlaylist",
+            AuditTargetType::Playlist,
+            playlist_uuid,
+            Some(old_value),
+            None,
+        );
+        Ok(true)
+    }
+
+    // Scheduling
+    pub async fn create_schedule_rule(
+        context: &GraphQLContext,
+        rule: ScheduleRuleInput,
+    ) -> FieldResult<ScheduleRule> {
+        require_admin(context)?;
+        let rule = graphql_translate_anyhow(ScheduleSvc::create(context, rule).await)?;
+        AuditSvc::record(
+            context,
+            "createScheduleRule",
+            AuditTargetType::ScheduleRule,
+            rule.uuid,
+            None,
+            Some(schedule_rule_snapshot(&rule)),
+        );
+        Ok(rule)
+    }
+    pub async fn update_schedule_rule(
+        context: &GraphQLContext,
+        rule_uuid: Uuid,
+        rule: ScheduleRuleInput,
+    ) -> FieldResult<ScheduleRule> {
+        require_admin(context)?;
+        let existing = graphql_translate_anyhow(ScheduleSvc::get(context, rule_uuid))?;
+        let rule = graphql_translate_anyhow(ScheduleSvc::update(context, rule_uuid, rule).await)?;
+        AuditSvc::record(
+            context,
+            "updateScheduleRule",
+            AuditTargetType::ScheduleRule,
+            rule_uuid,
+            Some(schedule_rule_snapshot(&existing)),
+            Some(schedule_rule_snapshot(&rule)),
+        );
+        Ok(rule)
+    }
+    pub async fn delete_schedule_rule(
+        context: &GraphQLContext,
+        rule_uuid: Uuid,
+    ) -> FieldResult<bool> {
+        require_admin(context)?;
+        let existing = graphql_translate_anyhow(ScheduleSvc::get(context, rule_uuid))?;
+        graphql_translate_anyhow(ScheduleSvc::delete(context, rule_uuid).await)?;
+        AuditSvc::record(
+            context,
+            "deleteScheduleRule",
+            AuditTargetType::ScheduleRule,
+            rule_uuid,
+            Some(schedule_rule_snapshot(&existing)),
+            None,
+        );
+        Ok(true)
+    }
+    pub async fn set_timezone(context: &GraphQLContext, timezone: String) -> FieldResult<bool> {
+        require_admin(context)?;
+        let timezone = timezone
+            .parse::<chrono_tz::Tz>()
+            .map_err(|e| FieldError::new(e.to_string(), juniper::Value::Null))?;
+        let old_timezone = ConfigSvc::get_timezone(context)
+            .await
+            .map_err(|e| FieldError::new(e.to_string(), juniper::Value::Null))?;
+        ConfigSvc::set_timezone(context, timezone)
+            .await
+            .map_err(|e| FieldError::new(e.to_string(), juniper::Value::Null))?;
+        AuditSvc::record(
+            context,
+            "setTimezone",
+            AuditTargetType::Config,
+            "timezone",
+            Some(json!(old_timezone.name())),
+            Some(json!(timezone.name())),
+        );
+        for screensaver in context.screensavers() {
+            graphql_translate_anyhow(screensaver.on_schedule_changed().await)?;
+        }
+        Ok(true)
     }
 
     // Screensaver controls
-    pub async fn pause_screensaver(context: &GraphQLContext) -> FieldResult<bool> {
-        if let Some(screensaver) = &context.screensaver {
+    pub async fn set_quiet_hours(
+        context: &GraphQLContext,
+        quiet_hours: Vec<QuietHoursInput>,
+    ) -> FieldResult<Vec<QuietHours>> {
+        require_admin(context)?;
+        let old_quiet_hours = ConfigSvc::get_quiet_hours(context)
+            .await
+            .map_err(|e| FieldError::new(e.to_string(), juniper::Value::Null))?;
+        let quiet_hours = graphql_translate_anyhow(QuietHoursSvc::set(context, quiet_hours).await)?;
+        AuditSvc::record(
+            context,
+            "setQuietHours",
+            AuditTargetType::Config,
+            "quiet_hours",
+            Some(quiet_hours_snapshot(&old_quiet_hours)),
+            Some(quiet_hours_snapshot(&quiet_hours)),
+        );
+        Ok(quiet_hours)
+    }
+    pub async fn set_quiet_frame(context: &GraphQLContext, frame: QuietFrame) -> FieldResult<bool> {
+        require_admin(context)?;
+        let old_frame = ConfigSvc::get_quiet_frame(context)
+            .await
+            .map_err(|e| FieldError::new(e.to_string(), juniper::Value::Null))?;
+        graphql_translate_anyhow(QuietHoursSvc::set_frame(context, frame).await)?;
+        AuditSvc::record(
+            context,
+            "setQuietFrame",
+            AuditTargetType::Config,
+            "quiet_hours.frame",
+            Some(json!(old_frame.as_str())),
+            Some(json!(frame.as_str())),
+        );
+        Ok(true)
+    }
+    pub async fn set_transition(
+        context: &GraphQLContext,
+        transition: Transition,
+        duration_ms: Option<i32>,
+    ) -> FieldResult<bool> {
+        require_admin(context)?;
+        if duration_ms.is_some_and(|duration_ms| duration_ms <= 0) {
+            return Err(FieldError::new(
+                "Transition duration must be positive",
+                juniper::Value::Null,
+            ));
+        }
+        let old_value = transition_snapshot(context).await?;
+        graphql_translate_anyhow(
+            TransitionSvc::set(
+                context,
+                transition,
+                duration_ms.map(|duration_ms| duration_ms as u64),
+            )
+            .await,
+        )?;
+        AuditSvc::record(
+            context,
+            "setTransition",
+            AuditTargetType::Config,
+            "transition",
+            Some(old_value),
+            Some(transition_snapshot(context).await?),
+        );
+        Ok(true)
+    }
+    pub async fn pause_screensaver(
+        context: &GraphQLContext,
+        display: Option<DisplayFormat>,
+    ) -> FieldResult<bool> {
+        require_scope(context, ApiScope::ScreensaverControl)?;
+        if let Some(screensaver) =
+            context.screensaver_for(display.unwrap_or(DisplayFormat::RGB320x240))
+        {
+            let state = screensaver.get_state().await;
             graphql_translate_anyhow(screensaver.pause().await)?;
+            AuditSvc::record(
+                context,
+                "pauseScreensaver",
+                AuditTargetType::Display,
+                screensaver.display().as_str(),
+                Some(json!({ "running": state.is_running })),
+                Some(json!({ "running": false })),
+            );
             Ok(true)
         } else {
             Err(FieldError::new(
@@ -78,9 +1208,24 @@ impl Mutation {
         }
     }
 
-    pub async fn resume_screensaver(context: &GraphQLContext) -> FieldResult<bool> {
-        if let Some(screensaver) = &context.screensaver {
+    pub async fn resume_screensaver(
+        context: &GraphQLContext,
+        display: Option<DisplayFormat>,
+    ) -> FieldResult<bool> {
+        require_scope(context, ApiScope::ScreensaverControl)?;
+        if let Some(screensaver) =
+            context.screensaver_for(display.unwrap_or(DisplayFormat::RGB320x240))
+        {
+            let state = screensaver.get_state().await;
             graphql_translate_anyhow(screensaver.resume().await)?;
+            AuditSvc::record(
+                context,
+                "resumeScreensaver",
+                AuditTargetType::Display,
+                screensaver.display().as_str(),
+                Some(json!({ "running": state.is_running })),
+                Some(json!({ "running": true })),
+            );
             Ok(true)
         } else {
             Err(FieldError::new(
@@ -90,9 +1235,24 @@ impl Mutation {
         }
     }
 
-    pub async fn next_image(context: &GraphQLContext) -> FieldResult<bool> {
-        if let Some(screensaver) = &context.screensaver {
+    pub async fn next_image(
+        context: &GraphQLContext,
+        display: Option<DisplayFormat>,
+    ) -> FieldResult<bool> {
+        require_scope(context, ApiScope::ScreensaverControl)?;
+        if let Some(screensaver) =
+            context.screensaver_for(display.unwrap_or(DisplayFormat::RGB320x240))
+        {
+            let state = screensaver.get_state().await;
             graphql_translate_anyhow(screensaver.advance_to_next_image().await)?;
+            AuditSvc::record(
+                context,
+                "nextImage",
+                AuditTargetType::Display,
+                screensaver.display().as_str(),
+                Some(current_upload_snapshot(&state)),
+                Some(current_upload_snapshot(&screensaver.get_state().await)),
+            );
             Ok(true)
         } else {
             Err(FieldError::new(
@@ -102,9 +1262,24 @@ impl Mutation {
         }
     }
 
-    pub async fn previous_image(context: &GraphQLContext) -> FieldResult<bool> {
-        if let Some(screensaver) = &context.screensaver {
+    pub async fn previous_image(
+        context: &GraphQLContext,
+        display: Option<DisplayFormat>,
+    ) -> FieldResult<bool> {
+        require_scope(context, ApiScope::ScreensaverControl)?;
+        if let Some(screensaver) =
+            context.screensaver_for(display.unwrap_or(DisplayFormat::RGB320x240))
+        {
+            let state = screensaver.get_state().await;
             graphql_translate_anyhow(screensaver.go_to_previous_image().await)?;
+            AuditSvc::record(
+                context,
+                "previousImage",
+                AuditTargetType::Display,
+                screensaver.display().as_str(),
+                Some(current_upload_snapshot(&state)),
+                Some(current_upload_snapshot(&screensaver.get_state().await)),
+            );
             Ok(true)
         } else {
             Err(FieldError::new(
@@ -114,18 +1289,100 @@ impl Mutation {
         }
     }
 
+    pub async fn show_upload(
+        context: &GraphQLContext,
+        upload_uuid: Uuid,
+        pin_seconds: Option<i32>,
+    ) -> FieldResult<bool> {
+        if pin_seconds.is_some() {
+            // A pin holds the display for everyone, like changing its settings
+            require_admin_scope(context, ApiScope::ScreensaverControl)?;
+        } else {
+            require_scope(context, ApiScope::ScreensaverControl)?;
+        }
+        if pin_seconds.is_some_and(|seconds| seconds <= 0) {
+            return Err(FieldError::new(
+                "Pin duration must be positive",
+                juniper::Value::Null,
+            ));
+        }
+        let upload = graphql_translate_anyhow(UploadSvc::get_visible(context, upload_uuid))?;
+        let screensaver = upload
+            .display_format()
+            .and_then(|display| context.screensaver_for(display))
+            .ok_or_else(|| {
+                FieldError::new("Screensaver service not available", juniper::Value::Null)
+            })?;
+        let state = screensaver.get_state().await;
+        graphql_translate_anyhow(
+            screensaver
+                .show_upload(&upload, pin_seconds.map(|seconds| seconds as u64))
+                .await,
+        )?;
+        AuditSvc::record(
+            context,
+            "showUpload",
+            AuditTargetType::Display,
+            screensaver.display().as_str(),
+            Some(current_upload_snapshot(&state)),
+            Some(json!({ "uploadUuid": upload_uuid, "pinSeconds": pin_seconds })),
+        );
+        Ok(true)
+    }
+
     pub async fn set_screensaver_interval(
         context: &GraphQLContext,
         seconds: i32,
+        display: Option<DisplayFormat>,
     ) -> FieldResult<bool> {
-        if let Some(screensaver) = &context.screensaver {
+        require_admin_scope(context, ApiScope::ScreensaverControl)?;
+        if let Some(screensaver) =
+            context.screensaver_for(display.unwrap_or(DisplayFormat::RGB320x240))
+        {
             if seconds <= 0 {
                 return Err(FieldError::new(
                     "Interval must be positive",
                     juniper::Value::Null,
                 ));
             }
+            let state = screensaver.get_state().await;
             graphql_translate_anyhow(screensaver.set_interval(seconds as u64).await)?;
+            AuditSvc::record(
+                context,
+                "setScreensaverInterval",
+                AuditTargetType::Display,
+                screensaver.display().as_str(),
+                Some(json!({ "intervalSeconds": state.interval_seconds })),
+                Some(json!({ "intervalSeconds": seconds })),
+            );
+            Ok(true)
+        } else {
+            Err(FieldError::new(
+                "Screensaver service not available",
+                juniper::Value::Null,
+            ))
+        }
+    }
+
+    pub async fn set_screensaver_mode(
+        context: &GraphQLContext,
+        mode: SlideshowMode,
+        display: Option<DisplayFormat>,
+    ) -> FieldResult<bool> {
+        require_admin_scope(context, ApiScope::ScreensaverControl)?;
+        if let Some(screensaver) =
+            context.screensaver_for(display.unwrap_or(DisplayFormat::RGB320x240))
+        {
+            let state = screensaver.get_state().await;
+            graphql_translate_anyhow(screensaver.set_mode(mode).await)?;
+            AuditSvc::record(
+                context,
+                "setScreensaverMode",
+                AuditTargetType::Display,
+                screensaver.display().as_str(),
+                Some(json!({ "mode": state.mode.as_str() })),
+                Some(json!({ "mode": mode.as_str() })),
+            );
             Ok(true)
         } else {
             Err(FieldError::new(
@@ -134,19 +1391,359 @@ impl Mutation {
             ))
         }
     }
+
+    pub async fn set_screensaver_album(
+        context: &GraphQLContext,
+        album_uuid: Option<Uuid>,
+        display: Option<DisplayFormat>,
+    ) -> FieldResult<bool> {
+        require_admin_scope(context, ApiScope::ScreensaverControl)?;
+        if let Some(screensaver) =
+            context.screensaver_for(display.unwrap_or(DisplayFormat::RGB320x240))
+        {
+            if let Some(album_uuid) = album_uuid {
+                graphql_translate_anyhow(AlbumSvc::get(context, album_uuid))?;
+            }
+            let state = screensaver.get_state().await;
+            graphql_translate_anyhow(screensaver.set_album(album_uuid).await)?;
+            AuditSvc::record(
+                context,
+                "setScreensaverAlbum",
+                AuditTargetType::Display,
+                screensaver.display().as_str(),
+                Some(json!({ "albumUuid": state.album })),
+                Some(json!({ "albumUuid": album_uuid })),
+            );
+            Ok(true)
+        } else {
+            Err(FieldError::new(
+                "Screensaver service not available",
+                juniper::Value::Null,
+            ))
+        }
+    }
+
+    pub async fn set_screensaver_playlist(
+        context: &GraphQLContext,
+        playlist_uuid: Option<Uuid>,
+        display: Option<DisplayFormat>,
+    ) -> FieldResult<bool> {
+        require_admin_scope(context, ApiScope::ScreensaverControl)?;
+        if let Some(screensaver) =
+            context.screensaver_for(display.unwrap_or(DisplayFormat::RGB320x240))
+        {
+            if let Some(playlist_uuid) = playlist_uuid {
+                graphql_translate_anyhow(PlaylistSvc::get(context, playlist_uuid))?;
+            }
+            let state = screensaver.get_state().await;
+            graphql_translate_anyhow(screensaver.set_playlist(playlist_uuid).await)?;
+            AuditSvc::record(
+                context,
+                "setScreensaverPlaylist",
+                AuditTargetType::Display,
+                screensaver.display().as_str(),
+                Some(json!({ "playlistUuid": state.playlist })),
+                Some(json!({ "playlistUuid": playlist_uuid })),
+            );
+            Ok(true)
+        } else {
+            Err(FieldError::new(
+                "Screensaver service not available",
+                juniper::Value::Null,
+            ))
+        }
+    }
+}
+
+pub type EventStream<T> = Pin<Box<dyn Stream<Item = Result<T, FieldError>> + Send>>;
+
+pub struct Subscription;
+
+#[juniper::graphql_subscription(context = GraphQLContext)]
+impl Subscription {
+    /// New uploads, private ones only reach their owner and admins
+    pub async fn upload_created(context: &GraphQLContext) -> EventStream<Upload> {
+        let user = context.user.clone();
+        event_stream(context, move |event| match event {
+            Event::UploadCreated(upload) if upload.visible_to(user.as_ref()) => Some(*upload),
+            _ => None,
+        })
+    }
+    /// Slideshow changes, private uploads are left out unless the subscriber may see them
+    pub async fn screensaver_changed(
+        context: &GraphQLContext,
+        display: Option<DisplayFormat>,
+    ) -> EventStream<ScreensaverChange> {
+        let viewer = context.clone();
+        event_stream(context, move |event| match event {
+            Event::ScreensaverChanged(change)
+                if display.is_none_or(|display| display == change.display) =>
+            {
+                Some(ScreensaverChange {
+                    current_upload_uuid: visible_upload_uuid(&viewer, change.current_upload_uuid),
+                    pinned_upload_uuid: visible_upload_uuid(&viewer, change.pinned_upload_uuid),
+                    ..change
+                })
+            }
+            _ => None,
+        })
+    }
+    pub async fn device_status_changed(context: &GraphQLContext) -> EventStream<DeviceStatus> {
+        event_stream(context, |event| match event {
+            Event::DeviceStatusChanged(status) => Some(status),
+            _ => None,
+        })
+    }
+}
+
+/// `upload` if the viewer may see it, the slideshow also shows private uploads
+fn visible_upload_uuid(context: &GraphQLContext, upload: Option<Uuid>) -> Option<Uuid> {
+    upload.filter(|upload| UploadSvc::get_visible(context, *upload).is_ok())
+}
+
+/// Stream the events picked out by `select` until the server shuts down
+fn event_stream<T, F>(context: &GraphQLContext, select: F) -> EventStream<T>
+where
+    T: Send + 'static,
+    F: Fn(Event) -> Option<T> + Send + 'static,
+{
+    let mut rx = context.events.subscribe();
+    Box::pin(async_stream::stream! {
+        loop {
+            match rx.recv().await {
+                Ok(event) => {
+                    if let Some(item) = select(event) {
+                        yield Ok(item);
+                    }
+                }
+                Err(RecvError::Lagged(missed)) => {
+                    warn!("Subscriber fell behind, skipped {} events", missed);
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    })
 }
 
-pub type Schema = RootNode<Query, Mutation, EmptySubscription<GraphQLContext>>;
+pub type Schema = RootNode<Query, Mutation, Subscription>;
 pub fn create_schema() -> Schema {
-    Schema::new(Query, Mutation, EmptySubscription::new())
+    Schema::new(Query, Mutation, Subscription)
+}
+
+/// The logged in user, mutations can't be used anonymously. API tokens can only use the
+/// mutations their scopes allow.
+fn require_user(context: &GraphQLContext) -> FieldResult<&User> {
+    let user = context
+        .user
+        .as_ref()
+        .ok_or_else(|| FieldError::new("Not logged in", juniper::Value::Null))?;
+    if context.scopes.is_some() {
+        return Err(FieldError::new(
+            "API tokens can't do this, log in instead",
+            juniper::Value::Null,
+        ));
+    }
+    Ok(user)
+}
+
+/// The logged in user or the user of an API token with `scope`
+fn require_scope(context: &GraphQLContext, scope: ApiScope) -> FieldResult<&User> {
+    let user = context
+        .user
+        .as_ref()
+        .ok_or_else(|| FieldError::new("Not logged in", juniper::Value::Null))?;
+    if !context.has_scope(scope) {
+        return Err(FieldError::new(
+            format!("API token lacks the {} scope", scope.as_str()),
+            juniper::Value::Null,
+        ));
+    }
+    Ok(user)
+}
+
+/// The logged in user if they are an admin
+fn require_admin(context: &GraphQLContext) -> FieldResult<&User> {
+    let user = require_user(context)?;
+    if !user.is_admin {
+        return Err(FieldError::new(
+            "Only admins can do this",
+            juniper::Value::Null,
+        ));
+    }
+    Ok(user)
+}
+
+/// An admin, logged in or through an API token with `scope`. For settings of the whole
+/// installation that scripts may still change.
+fn require_admin_scope(context: &GraphQLContext, scope: ApiScope) -> FieldResult<&User> {
+    let user = require_scope(context, scope)?;
+    if !user.is_admin {
+        return Err(FieldError::new(
+            "Only admins can do this",
+            juniper::Value::Null,
+        ));
+    }
+    Ok(user)
+}
+
+async fn load_upload_limits(context: &GraphQLContext) -> FieldResult<UploadLimits> {
+    let to_field_error = |e: Box<dyn std::error::Error + Send + Sync>| {
+        FieldError::new(e.to_string(), juniper::Value::Null)
+    };
+    Ok(UploadLimits {
+        uploads_per_minute_ip: ConfigSvc::get_uploads_per_minute_ip(context)
+            .await
+            .map_err(to_field_error)? as i32,
+        uploads_per_minute_user: ConfigSvc::get_uploads_per_minute_user(context)
+            .await
+            .map_err(to_field_error)? as i32,
+        storage_quota_mb: ConfigSvc::get_storage_quota_mb(context)
+            .await
+            .map_err(to_field_error)? as i32,
+    })
+}
+
+/// A playlist with its items, as kept in the audit log
+fn playlist_snapshot(context: &GraphQLContext, playlist: &Playlist) -> FieldResult<Value> {
+    let items = graphql_translate_anyhow(PlaylistSvc::list_items(context, playlist.uuid.into()))?;
+    let items: Vec<Value> = items
+        .iter()
+        .map(|item| {
+            json!({
+                "uploadUuid": Uuid::from(item.upload_uuid),
+                "durationSeconds": item.duration_seconds,
+            })
+        })
+        .collect();
+    Ok(json!({ "name": playlist.name, "items": items }))
+}
+
+/// The transition settings, as kept in the audit log
+async fn transition_snapshot(context: &GraphQLContext) -> FieldResult<Value> {
+    let transition = ConfigSvc::get_transition(context)
+        .await
+        .map_err(|e| FieldError::new(e.to_string(), juniper::Value::Null))?;
+    let duration_ms = ConfigSvc::get_transition_duration_ms(context)
+        .await
+        .map_err(|e| FieldError::new(e.to_string(), juniper::Value::Null))?;
+    Ok(json!({ "transition": transition.as_str(), "durationMs": duration_ms }))
+}
+
+/// What a display shows, as kept in the audit log
+fn current_upload_snapshot(state: &ScreensaverState) -> Value {
+    let upload = state
+        .pinned
+        .map(|pinned| pinned.upload)
+        .or(state.current.map(|current| current.upload));
+    json!({ "uploadUuid": upload.map(Uuid::from) })
+}
+
+/// A limit error with a `code` extension, so clients can tell it apart from other errors
+fn limit_field_error(limit: &LimitError) -> FieldError {
+    match limit {
+        LimitError::RateLimited { retry_after } => {
+            let retry_after_seconds = retry_after.as_secs().max(1) as i32;
+            FieldError::new(
+                limit,
+                graphql_value!({
+                    "code": "RATE_LIMITED",
+                    "retryAfterSeconds": retry_after_seconds,
+                }),
+            )
+        }
+        LimitError::QuotaExceeded {
+            used_bytes,
+            quota_bytes,
+        } => {
+            let used_mb = *used_bytes as f64 / BYTES_PER_MB as f64;
+            let quota_mb = (quota_bytes / BYTES_PER_MB) as i32;
+            FieldError::new(
+                limit,
+                graphql_value!({
+                    "code": "QUOTA_EXCEEDED",
+                    "usedMb": used_mb,
+                    "quotaMb": quota_mb,
+                }),
+            )
+        }
+    }
+}
+
+/// An `INVALID_INPUT` error listing what is wrong with each field
+fn validation_field_error(invalid: &ValidationError) -> FieldError {
+    let errors = invalid
+        .0
+        .iter()
+        .map(|violation| {
+            let field = violation.field.to_owned();
+            let message = violation.message.clone();
+            graphql_value!({ "field": field, "message": message })
+        })
+        .collect();
+    let errors = juniper::Value::list(errors);
+    FieldError::new(
+        invalid,
+        graphql_value!({ "code": "INVALID_INPUT", "errors": errors }),
+    )
 }
 
 pub fn graphql_translate_anyhow<T>(res: anyhow::Result<T>) -> FieldResult<T> {
     match res {
         Ok(t) => Ok(t),
         Err(e) => {
+            if let Some(limit) = e.downcast_ref::<LimitError>() {
+                warn!("{}", limit);
+                return Err(limit_field_error(limit));
+            }
+            if let Some(invalid) = e.downcast_ref::<ValidationError>() {
+                return Err(validation_field_error(invalid));
+            }
             error!("Could not upload: {:?}", e);
             Err(FieldError::from(e))
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db::get_conn, schema::uploads, uuid::UUID};
+    use diesel::prelude::*;
+
+    fn insert_upload(context: &GraphQLContext, public: bool) -> Uuid {
+        let upload = Upload {
+            uuid: UUID::random(),
+            message: None,
+            data: Vec::new(),
+            public,
+            uploaded_at: None,
+            name: None,
+            display: Some(DisplayFormat::RGB320x240.to_string()),
+            deleted_at: None,
+            weight: 1,
+            favorite: false,
+            owner_uuid: None,
+            status: "approved".to_owned(),
+            moderated_by: None,
+            moderated_at: None,
+            rejection_reason: None,
+            content_hash: None,
+        };
+        diesel::insert_into(uploads::table)
+            .values(&upload)
+            .execute(&mut get_conn(context))
+            .unwrap();
+        upload.uuid.into()
+    }
+
+    #[test]
+    fn it_hides_private_uploads_on_screen() {
+        let context = GraphQLContext::in_memory();
+        let public = insert_upload(&context, true);
+        let private = insert_upload(&context, false);
+
+        assert_eq!(visible_upload_uuid(&context, Some(public)), Some(public));
+        assert_eq!(visible_upload_uuid(&context, Some(private)), None);
+        assert_eq!(visible_upload_uuid(&context, None), None);
+    }
+}
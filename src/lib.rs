@@ -14,6 +14,7 @@ pub mod graphql;
 pub mod models;
 pub mod routes;
 pub mod schema;
+pub mod subscriptions;
 pub mod svc;
 pub mod uuid;
 
@@ -68,6 +69,11 @@ where
     }
 }
 
+/// Whether `PRODUCTION` is set, the GraphQL playgrounds and test routes are left out then
+pub fn is_production() -> bool {
+    get_env_typed("PRODUCTION", false)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,8 +1,13 @@
 #![allow(non_snake_case)]
 
 use anyhow::Result;
-use image_manager::{context::GraphQLContext, routes::app, svc::ScreensaverSvc};
-use std::sync::Arc;
+use image_manager::{
+    context::GraphQLContext,
+    models::DisplayFormat,
+    routes::app,
+    svc::{DuplicateSvc, EventBus, RateLimiter, ScreensaverSvc, TrashSvc, UserSvc},
+};
+use std::{net::SocketAddr, sync::Arc};
 
 use image_manager::db::get_pool;
 use image_manager::get_env_typed;
@@ -28,6 +33,12 @@ async fn main() -> Result<()> {
     let base_context = GraphQLContext { 
         pool: get_pool(),
         screensaver: None,
+        mono_screensaver: None,
+        events: EventBus::default(),
+        user: None,
+        scopes: None,
+        client_ip: None,
+        rate_limiter: RateLimiter::default(),
     };
 
     let mut conn = base_context
@@ -41,18 +52,45 @@ async fn main() -> Result<()> {
         Err(e) => error!("Could not run migrations {:?}", e),
     };
 
-    // Create screensaver service and final context
-    let screensaver_svc = Arc::new(ScreensaverSvc::new(Arc::new(base_context.clone())));
+    if let Err(e) = UserSvc::bootstrap_admin(&base_context) {
+        error!("Could not create admin account: {:?}", e);
+    }
+    match DuplicateSvc::backfill(&base_context) {
+        Ok(0) => {}
+        Ok(hashed) => info!("Hashed {} existing uploads", hashed),
+        Err(e) => error!("Could not hash existing uploads: {:?}", e),
+    }
+
+    // Create a screensaver service per display and the final context
+    let screensaver_svc = Arc::new(ScreensaverSvc::new(
+        Arc::new(base_context.clone()),
+        DisplayFormat::RGB320x240,
+    ));
+    let mono_screensaver_svc = Arc::new(ScreensaverSvc::new(
+        Arc::new(base_context.clone()),
+        DisplayFormat::Esp32,
+    ));
     let context = GraphQLContext {
         pool: base_context.pool,
         screensaver: Some(screensaver_svc.clone()),
+        mono_screensaver: Some(mono_screensaver_svc.clone()),
+        events: base_context.events,
+        user: None,
+        scopes: None,
+        client_ip: None,
+        rate_limiter: base_context.rate_limiter,
     };
 
-    // Start screensaver service
-    if let Err(e) = screensaver_svc.start().await {
-        error!("Failed to start screensaver service: {}", e);
+    // Start screensaver services
+    for screensaver in context.screensavers() {
+        if let Err(e) = screensaver.start().await {
+            error!("Failed to start screensaver service: {}", e);
+        }
     }
 
+    // Start purging expired uploads from the trash
+    TrashSvc::start(Arc::new(context.clone()));
+
     let app = app(context.clone());
 
     let (tx, mut rx) = mpsc::channel(1);
@@ -62,17 +100,21 @@ async fn main() -> Result<()> {
         .await
         .unwrap();
     info!("listener set up at {listen_address}:{port}");
-    axum::serve(listener, app)
-        .with_graceful_shutdown(async move {
-            tokio::signal::ctrl_c()
-                .await
-                .expect("failed to listen for shutdown signal");
-            tx.send(())
-                .await
-                .expect("could not send shutdown signal to thread");
-        })
-        .await
-        .expect("Could not keep server open");
+    // Handlers need the peer address for the per IP upload limits
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(async move {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to listen for shutdown signal");
+        tx.send(())
+            .await
+            .expect("could not send shutdown signal to thread");
+    })
+    .await
+    .expect("Could not keep server open");
 
     rx.recv().await;
 
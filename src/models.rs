@@ -5,7 +5,10 @@
 
 use std::str::FromStr;
 
-use crate::svc::packed_to_png;
+use crate::svc::{
+    decode_data, packed_to_png, validate_upload, AlbumSvc, DuplicateSvc, HistorySvc, LimitSvc,
+    PlaylistSvc, ScheduleSvc, ShareSvc, TagSvc, UserSvc, ValidationError, BYTES_PER_MB,
+};
 use anyhow::Result;
 use bigdecimal::{FromPrimitive, ToPrimitive};
 use chrono::NaiveDateTime;
@@ -17,7 +20,7 @@ use crate::{context::GraphQLContext, schema::*, svc::UploadSvc, uuid::UUID};
 use bigdecimal::BigDecimal;
 use diesel::prelude::*;
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, GraphQLEnum)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, GraphQLEnum)]
 pub enum DisplayFormat {
     #[serde(rename = "RGB_320x240")]
     #[graphql(name = "RGB_320x240")]
@@ -53,6 +56,59 @@ impl ToString for DisplayFormat {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, GraphQLEnum)]
+pub enum SlideshowMode {
+    /// Step through the slides in order
+    Sequential,
+    /// Play every slide once in random order before reshuffling
+    Shuffle,
+    /// Pick slides at random, favoring uploads with a higher weight
+    Weighted,
+}
+impl SlideshowMode {
+    pub(crate) fn as_str(&self) -> &str {
+        match self {
+            SlideshowMode::Sequential => "sequential",
+            SlideshowMode::Shuffle => "shuffle",
+            SlideshowMode::Weighted => "weighted",
+        }
+    }
+}
+
+impl FromStr for SlideshowMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sequential" => Ok(SlideshowMode::Sequential),
+            "shuffle" => Ok(SlideshowMode::Shuffle),
+            "weighted" => Ok(SlideshowMode::Weighted),
+            _ => Err(anyhow::anyhow!("Invalid slideshow mode: {}", s)),
+        }
+    }
+}
+
+/// Published whenever a slideshow moves to another upload or its settings change
+#[derive(Debug, Clone, GraphQLObject)]
+pub struct ScreensaverChange {
+    pub display: DisplayFormat,
+    pub current_upload_uuid: Option<Uuid>,
+    /// Upload shown by `showUpload` while the slideshow holds
+    pub pinned_upload_uuid: Option<Uuid>,
+    pub is_running: bool,
+    pub interval_seconds: i32,
+}
+
+/// Whether the last push to a display reached it
+#[derive(Debug, Clone, GraphQLObject)]
+pub struct DeviceStatus {
+    pub display: DisplayFormat,
+    pub online: bool,
+    /// Why the last push failed, if it did
+    pub error: Option<String>,
+    pub checked_at: NaiveDateTime,
+}
+
 #[derive(
     Queryable, Debug, Identifiable, Insertable, Selectable, AsChangeset, PartialEq, Eq, Clone, Hash,
 )]
@@ -65,6 +121,38 @@ pub struct Upload {
     pub uploaded_at: Option<NaiveDateTime>,
     pub name: Option<String>,
     pub display: Option<String>,
+    pub deleted_at: Option<NaiveDateTime>,
+    pub weight: i32,
+    pub favorite: bool,
+    pub owner_uuid: Option<UUID>,
+    pub status: String,
+    pub moderated_by: Option<UUID>,
+    pub moderated_at: Option<NaiveDateTime>,
+    pub rejection_reason: Option<String>,
+    pub content_hash: Option<String>,
+}
+
+impl Upload {
+    /// The display this upload is meant for, if it names a known one
+    pub fn display_format(&self) -> Option<DisplayFormat> {
+        self.display.as_deref().and_then(|display| display.parse().ok())
+    }
+
+    /// Whether a moderator let the upload through, only approved uploads reach the displays
+    pub fn is_approved(&self) -> bool {
+        self.status == ModerationStatus::Approved.as_str()
+    }
+
+    /// Whether `user` may see this upload. Private uploads and those that aren't approved are only
+    /// for their owner and admins.
+    pub fn visible_to(&self, user: Option<&User>) -> bool {
+        (self.public && self.is_approved()) || self.manageable_by(user)
+    }
+
+    /// Whether `user` may change or delete this upload. Uploads without an owner are admin only.
+    pub fn manageable_by(&self, user: Option<&User>) -> bool {
+        user.is_some_and(|user| user.is_admin || self.owner_uuid == Some(user.uuid))
+    }
 }
 
 #[juniper::graphql_object(context = GraphQLContext)]
@@ -95,6 +183,55 @@ impl Upload {
         self.display.as_deref()
     }
 
+    pub fn deleted_at(&self) -> Option<NaiveDateTime> {
+        self.deleted_at
+    }
+
+    pub fn weight(&self) -> i32 {
+        self.weight
+    }
+
+    pub fn favorite(&self) -> bool {
+        self.favorite
+    }
+
+    /// The user who uploaded it, `null` for uploads from before accounts existed
+    pub fn owner_uuid(&self) -> Option<Uuid> {
+        self.owner_uuid.map(Uuid::from)
+    }
+
+    pub fn status(&self) -> Result<ModerationStatus> {
+        self.status.parse()
+    }
+
+    pub fn moderated_at(&self) -> Option<NaiveDateTime> {
+        self.moderated_at
+    }
+
+    pub fn rejection_reason(&self) -> Option<&str> {
+        self.rejection_reason.as_deref()
+    }
+
+    /// Hex SHA-256 of the data, identical images have the same hash
+    pub fn content_hash(&self) -> Option<&str> {
+        self.content_hash.as_deref()
+    }
+
+    /// Other uploads with the same data that the viewer may see
+    pub fn duplicates(&self, context: &GraphQLContext) -> Result<Vec<Upload>> {
+        let Some(content_hash) = &self.content_hash else {
+            return Ok(Vec::new());
+        };
+        Ok(DuplicateSvc::list_with_hash(context, content_hash)?
+            .into_iter()
+            .filter(|upload| upload.uuid != self.uuid && upload.visible_to(context.user.as_ref()))
+            .collect())
+    }
+
+    pub fn tags(&self, context: &GraphQLContext) -> Result<Vec<String>> {
+        TagSvc::list_for_upload(context, self.uuid)
+    }
+
     pub async fn img_src(&self) -> String {
         if self.display.as_deref() == Some(DisplayFormat::RGB320x240.as_str()) {
             return format!("data:image/jpeg;base64,{}", base64::encode(&self.data));
@@ -104,7 +241,363 @@ impl Upload {
     }
 }
 
+#[derive(Queryable, Debug, Identifiable, Insertable, Selectable, AsChangeset, PartialEq, Eq, Clone)]
+#[diesel(primary_key(uuid), table_name = albums)]
+pub struct Album {
+    pub uuid: UUID,
+    pub name: String,
+    pub description: Option<String>,
+    pub created_at: Option<NaiveDateTime>,
+}
+
+#[juniper::graphql_object(context = GraphQLContext)]
+impl Album {
+    pub fn uuid(&self) -> Uuid {
+        self.uuid.into()
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+    pub fn created_at(&self) -> Option<NaiveDateTime> {
+        self.created_at
+    }
+    /// The album's uploads, without private ones the viewer isn't allowed to see
+    pub fn uploads(&self, context: &GraphQLContext) -> Result<Vec<Upload>> {
+        let uploads = AlbumSvc::list_uploads(context, self.uuid.into())?;
+        Ok(uploads
+            .into_iter()
+            .filter(|upload| upload.visible_to(context.user.as_ref()))
+            .collect())
+    }
+}
+
+#[derive(Queryable, Insertable, Debug, Clone)]
+#[diesel(table_name = album_uploads)]
+pub struct AlbumUpload {
+    pub album_uuid: UUID,
+    pub upload_uuid: UUID,
+}
+
+#[derive(Queryable, Insertable, Debug, Clone)]
+#[diesel(table_name = upload_tags)]
+pub struct UploadTag {
+    pub upload_uuid: UUID,
+    pub tag: String,
+}
+
+#[derive(Queryable, Debug, Identifiable, Insertable, Selectable, AsChangeset, PartialEq, Eq, Clone)]
+#[diesel(primary_key(uuid), table_name = playlists)]
+pub struct Playlist {
+    pub uuid: UUID,
+    pub name: String,
+    pub created_at: Option<NaiveDateTime>,
+}
+
+#[juniper::graphql_object(context = GraphQLContext)]
+impl Playlist {
+    pub fn uuid(&self) -> Uuid {
+        self.uuid.into()
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn created_at(&self) -> Option<NaiveDateTime> {
+        self.created_at
+    }
+    pub fn items(&self, context: &GraphQLContext) -> Result<Vec<PlaylistItem>> {
+        PlaylistSvc::list_items(context, self.uuid.into())
+    }
+}
+
+#[derive(Queryable, Insertable, Selectable, Debug, Clone)]
+#[diesel(table_name = playlist_items)]
+pub struct PlaylistItem {
+    pub playlist_uuid: UUID,
+    pub position: i32,
+    pub upload_uuid: UUID,
+    pub duration_seconds: Option<i32>,
+}
+
+#[juniper::graphql_object(context = GraphQLContext)]
+impl PlaylistItem {
+    pub fn position(&self) -> i32 {
+        self.position
+    }
+    pub fn duration_seconds(&self) -> Option<i32> {
+        self.duration_seconds
+    }
+    /// Empty when the upload is private to someone else
+    pub fn upload(&self, context: &GraphQLContext) -> Option<Upload> {
+        UploadSvc::get_visible(context, self.upload_uuid.into()).ok()
+    }
+}
+
 #[derive(GraphQLInputObject, Debug, Clone)]
+pub struct PlaylistItemInput {
+    pub upload_uuid: Uuid,
+    pub duration_seconds: Option<i32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, GraphQLEnum)]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl From<chrono::Weekday> for Weekday {
+    fn from(weekday: chrono::Weekday) -> Self {
+        match weekday {
+            chrono::Weekday::Mon => Weekday::Monday,
+            chrono::Weekday::Tue => Weekday::Tuesday,
+            chrono::Weekday::Wed => Weekday::Wednesday,
+            chrono::Weekday::Thu => Weekday::Thursday,
+            chrono::Weekday::Fri => Weekday::Friday,
+            chrono::Weekday::Sat => Weekday::Saturday,
+            chrono::Weekday::Sun => Weekday::Sunday,
+        }
+    }
+}
+
+impl Weekday {
+    pub(crate) fn as_str(&self) -> &str {
+        match self {
+            Weekday::Monday => "monday",
+            Weekday::Tuesday => "tuesday",
+            Weekday::Wednesday => "wednesday",
+            Weekday::Thursday => "thursday",
+            Weekday::Friday => "friday",
+            Weekday::Saturday => "saturday",
+            Weekday::Sunday => "sunday",
+        }
+    }
+}
+
+/// What the RGB display shows during quiet hours
+#[derive(Debug, Clone, Copy, PartialEq, Eq, GraphQLEnum)]
+pub enum QuietFrame {
+    /// Turn every pixel off
+    Blank,
+    /// Keep the current image at low brightness
+    Dim,
+}
+impl QuietFrame {
+    pub(crate) fn as_str(&self) -> &str {
+        match self {
+            QuietFrame::Blank => "blank",
+            QuietFrame::Dim => "dim",
+        }
+    }
+}
+
+impl FromStr for QuietFrame {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "blank" => Ok(QuietFrame::Blank),
+            "dim" => Ok(QuietFrame::Dim),
+            _ => Err(anyhow::anyhow!("Invalid quiet frame: {}", s)),
+        }
+    }
+}
+
+/// Where an upload is in the moderation queue
+#[derive(Debug, Clone, Copy, PartialEq, Eq, GraphQLEnum)]
+pub enum ModerationStatus {
+    /// Waiting for a moderator, not shown on the displays
+    Pending,
+    Approved,
+    Rejected,
+}
+impl ModerationStatus {
+    pub(crate) fn as_str(&self) -> &str {
+        match self {
+            ModerationStatus::Pending => "pending",
+            ModerationStatus::Approved => "approved",
+            ModerationStatus::Rejected => "rejected",
+        }
+    }
+}
+
+impl FromStr for ModerationStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(ModerationStatus::Pending),
+            "approved" => Ok(ModerationStatus::Approved),
+            "rejected" => Ok(ModerationStatus::Rejected),
+            _ => Err(anyhow::anyhow!("Invalid moderation status: {}", s)),
+        }
+    }
+}
+
+/// What happens to an upload whose data is identical to an existing upload
+#[derive(Debug, Clone, Copy, PartialEq, Eq, GraphQLEnum)]
+pub enum DuplicatePolicy {
+    /// Turn the upload away
+    Reject,
+    /// Store it anyway and log a warning
+    Warn,
+    /// Don't store it, show and return the existing upload instead
+    Link,
+}
+impl DuplicatePolicy {
+    pub(crate) fn as_str(&self) -> &str {
+        match self {
+            DuplicatePolicy::Reject => "reject",
+            DuplicatePolicy::Warn => "warn",
+            DuplicatePolicy::Link => "link",
+        }
+    }
+}
+
+impl FromStr for DuplicatePolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reject" => Ok(DuplicatePolicy::Reject),
+            "warn" => Ok(DuplicatePolicy::Warn),
+            "link" => Ok(DuplicatePolicy::Link),
+            _ => Err(anyhow::anyhow!("Invalid duplicate policy: {}", s)),
+        }
+    }
+}
+
+/// How the RGB display changes from one slide to the next
+#[derive(Debug, Clone, Copy, PartialEq, Eq, GraphQLEnum)]
+pub enum Transition {
+    /// Replace the image at once
+    Cut,
+    /// Fade the new image in over the old one
+    Crossfade,
+    /// Reveal the new image from left to right
+    Wipe,
+    /// Push the old image out to the left
+    Slide,
+}
+impl Transition {
+    pub(crate) fn as_str(&self) -> &str {
+        match self {
+            Transition::Cut => "cut",
+            Transition::Crossfade => "crossfade",
+            Transition::Wipe => "wipe",
+            Transition::Slide => "slide",
+        }
+    }
+}
+
+impl FromStr for Transition {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cut" => Ok(Transition::Cut),
+            "crossfade" => Ok(Transition::Crossfade),
+            "wipe" => Ok(Transition::Wipe),
+            "slide" => Ok(Transition::Slide),
+            _ => Err(anyhow::anyhow!("Invalid transition: {}", s)),
+        }
+    }
+}
+
+/// Quiet hours of one weekday, a window ending before it starts runs past midnight
+#[derive(Debug, Clone, PartialEq, Eq, GraphQLObject)]
+pub struct QuietHours {
+    pub weekday: Weekday,
+    pub start_time: String,
+    pub end_time: String,
+}
+
+#[derive(GraphQLInputObject)]
+pub struct QuietHoursInput {
+    pub weekday: Weekday,
+    /// Local time as HH:MM
+    pub start_time: String,
+    /// Local time as HH:MM, may be before `startTime` to run past midnight
+    pub end_time: String,
+}
+
+#[derive(Queryable, Debug, Identifiable, Insertable, Selectable, AsChangeset, PartialEq, Eq, Clone)]
+#[diesel(primary_key(uuid), table_name = schedule_rules, treat_none_as_null = true)]
+pub struct ScheduleRule {
+    pub uuid: UUID,
+    pub name: String,
+    pub weekdays: i32,
+    pub day_of_month: Option<i32>,
+    pub start_time: String,
+    pub end_time: String,
+    pub upload_uuid: Option<UUID>,
+    pub playlist_uuid: Option<UUID>,
+    pub priority: i32,
+    pub enabled: bool,
+    pub created_at: Option<NaiveDateTime>,
+}
+
+#[juniper::graphql_object(context = GraphQLContext)]
+impl ScheduleRule {
+    pub fn uuid(&self) -> Uuid {
+        self.uuid.into()
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn weekdays(&self) -> Vec<Weekday> {
+        ScheduleSvc::mask_to_weekdays(self.weekdays)
+    }
+    pub fn day_of_month(&self) -> Option<i32> {
+        self.day_of_month
+    }
+    pub fn start_time(&self) -> &str {
+        &self.start_time
+    }
+    pub fn end_time(&self) -> &str {
+        &self.end_time
+    }
+    pub fn upload(&self, context: &GraphQLContext) -> Option<Upload> {
+        self.upload_uuid
+            .and_then(|uuid| UploadSvc::get_visible(context, uuid.into()).ok())
+    }
+    pub fn playlist(&self, context: &GraphQLContext) -> Option<Playlist> {
+        self.playlist_uuid
+            .and_then(|uuid| PlaylistSvc::get(context, uuid.into()).ok())
+    }
+    pub fn priority(&self) -> i32 {
+        self.priority
+    }
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+#[derive(GraphQLInputObject, Debug, Clone)]
+pub struct ScheduleRuleInput {
+    pub name: String,
+    /// Days the rule applies on, every day when omitted
+    pub weekdays: Option<Vec<Weekday>>,
+    /// Only apply on this day of the month (1-31)
+    pub day_of_month: Option<i32>,
+    /// Local start time as HH:MM
+    pub start_time: String,
+    /// Local end time as HH:MM, an end before the start wraps past midnight
+    pub end_time: String,
+    pub upload_uuid: Option<Uuid>,
+    pub playlist_uuid: Option<Uuid>,
+    /// Higher priority rules win when several are active, defaults to 0
+    pub priority: Option<i32>,
+    pub enabled: Option<bool>,
+}
+
+#[derive(GraphQLInputObject, Deserialize, Debug, Clone)]
 pub struct UploadInput {
     pub message: Option<String>,
     pub data: String,
@@ -113,16 +606,453 @@ pub struct UploadInput {
     pub display: DisplayFormat,
 }
 
-impl From<UploadInput> for Upload {
-    fn from(input: UploadInput) -> Self {
-        Self {
+/// Decodes and validates the input, so a bad upload is turned away before it is stored
+impl TryFrom<UploadInput> for Upload {
+    type Error = ValidationError;
+
+    fn try_from(input: UploadInput) -> Result<Self, Self::Error> {
+        let (data, bad_data) = match decode_data(&input.data) {
+            Ok(data) => (data, None),
+            Err(violation) => (Vec::new(), Some(violation)),
+        };
+        let upload = Self {
             uuid: UUID::random(),
             message: input.message,
-            data: base64::decode(input.data).unwrap_or_default(),
+            data,
             public: input.public,
             uploaded_at: Some(chrono::Utc::now().naive_utc()),
             name: input.name,
             display: Some(input.display.to_string()),
+            deleted_at: None,
+            weight: 1,
+            favorite: false,
+            owner_uuid: None,
+            status: ModerationStatus::Approved.as_str().to_owned(),
+            moderated_by: None,
+            moderated_at: None,
+            rejection_reason: None,
+            content_hash: None,
+        };
+
+        let Err(ValidationError(mut violations)) = validate_upload(&upload) else {
+            return match bad_data {
+                Some(violation) => Err(ValidationError(vec![violation])),
+                None => Ok(upload),
+            };
+        };
+        // The data can't be checked any further when it isn't even base64
+        if let Some(violation) = bad_data {
+            violations.retain(|violation| violation.field != "data");
+            violations.push(violation);
+        }
+        Err(ValidationError(violations))
+    }
+}
+
+#[derive(GraphQLInputObject, Debug, Clone)]
+pub struct UploadUpdateInput {
+    /// An empty string clears the message
+    pub message: Option<String>,
+    /// An empty string clears the name
+    pub name: Option<String>,
+    pub public: Option<bool>,
+    pub display: Option<DisplayFormat>,
+}
+
+impl UploadUpdateInput {
+    /// Apply the provided fields to an existing upload, leaving omitted fields untouched. An empty
+    /// name or message clears it.
+    pub fn apply_to(self, upload: &mut Upload) {
+        if let Some(message) = self.message {
+            upload.message = (!message.is_empty()).then_some(message);
+        }
+        if let Some(name) = self.name {
+            upload.name = (!name.is_empty()).then_some(name);
+        }
+        if let Some(public) = self.public {
+            upload.public = public;
+        }
+        if let Some(display) = self.display {
+            upload.display = Some(display.to_string());
+        }
+    }
+}
+
+/// What put a slide on screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, GraphQLEnum)]
+pub enum DisplayTrigger {
+    /// The slideshow interval or a pin ran out
+    Timer,
+    /// A mutation such as `nextImage` or `showUpload`
+    Manual,
+    /// The upload was just created
+    NewUpload,
+    /// A schedule rule or quiet hours started or ended
+    Schedule,
+}
+impl DisplayTrigger {
+    pub(crate) fn as_str(&self) -> &str {
+        match self {
+            DisplayTrigger::Timer => "timer",
+            DisplayTrigger::Manual => "manual",
+            DisplayTrigger::NewUpload => "new_upload",
+            DisplayTrigger::Schedule => "schedule",
+        }
+    }
+}
+
+impl FromStr for DisplayTrigger {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "timer" => Ok(DisplayTrigger::Timer),
+            "manual" => Ok(DisplayTrigger::Manual),
+            "new_upload" => Ok(DisplayTrigger::NewUpload),
+            "schedule" => Ok(DisplayTrigger::Schedule),
+            _ => Err(anyhow::anyhow!("Invalid display trigger: {}", s)),
+        }
+    }
+}
+
+/// One slide shown on a display, `ended_at` is empty while it is still on screen
+#[derive(Queryable, Debug, Identifiable, Insertable, Selectable, PartialEq, Eq, Clone)]
+#[diesel(primary_key(uuid), table_name = display_history)]
+pub struct DisplayHistory {
+    pub uuid: UUID,
+    pub display: String,
+    pub upload_uuid: UUID,
+    pub started_at: NaiveDateTime,
+    pub ended_at: Option<NaiveDateTime>,
+    pub triggered_by: String,
+}
+
+#[juniper::graphql_object(context = GraphQLContext)]
+impl DisplayHistory {
+    pub fn uuid(&self) -> Uuid {
+        self.uuid.into()
+    }
+    pub fn display(&self) -> Result<DisplayFormat> {
+        self.display.parse()
+    }
+    /// Empty when the upload is private or not approved
+    pub fn upload_uuid(&self, context: &GraphQLContext) -> Option<Uuid> {
+        HistorySvc::upload(context, self.upload_uuid).0
+    }
+    /// The upload, also when it is in the trash. Empty once it was purged or when it is private.
+    pub fn upload(&self, context: &GraphQLContext) -> Option<Upload> {
+        HistorySvc::upload(context, self.upload_uuid).1
+    }
+    pub fn started_at(&self) -> NaiveDateTime {
+        self.started_at
+    }
+    pub fn ended_at(&self) -> Option<NaiveDateTime> {
+        self.ended_at
+    }
+    pub fn trigger(&self) -> Result<DisplayTrigger> {
+        self.triggered_by.parse()
+    }
+}
+
+#[derive(Queryable, Debug, Identifiable, Insertable, Selectable, AsChangeset, PartialEq, Eq, Clone)]
+#[diesel(primary_key(uuid), table_name = users)]
+pub struct User {
+    pub uuid: UUID,
+    pub username: String,
+    pub password_hash: String,
+    pub is_admin: bool,
+    pub created_at: Option<NaiveDateTime>,
+    pub trusted: bool,
+    pub storage_quota_mb: Option<i32>,
+}
+
+#[juniper::graphql_object(context = GraphQLContext)]
+impl User {
+    pub fn uuid(&self) -> Uuid {
+        self.uuid.into()
+    }
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+    pub fn is_admin(&self) -> bool {
+        self.is_admin
+    }
+    pub fn created_at(&self) -> Option<NaiveDateTime> {
+        self.created_at
+    }
+    /// Trusted users' uploads skip the moderation queue
+    pub fn trusted(&self) -> bool {
+        self.trusted
+    }
+    /// Storage quota of this user, `null` when the default quota applies
+    pub fn storage_quota_mb(&self) -> Option<i32> {
+        self.storage_quota_mb
+    }
+    /// Megabytes taken up by the user's uploads, including those in the trash
+    pub fn storage_used_mb(&self, context: &GraphQLContext) -> Result<f64> {
+        let bytes = LimitSvc::storage_used(context, self.uuid.into())?;
+        Ok(bytes as f64 / BYTES_PER_MB as f64)
+    }
+}
+
+#[derive(GraphQLInputObject, Debug, Clone)]
+pub struct UserInput {
+    pub username: String,
+    pub password: String,
+    /// Admins manage accounts, defaults to false
+    pub is_admin: Option<bool>,
+    /// Trusted users' uploads skip the moderation queue, defaults to false
+    pub trusted: Option<bool>,
+}
+
+#[derive(Queryable, Debug, Insertable, Selectable, PartialEq, Eq, Clone)]
+#[diesel(table_name = sessions)]
+pub struct Session {
+    pub token_hash: String,
+    pub user_uuid: UUID,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+}
+
+/// What an API token may be used for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, GraphQLEnum)]
+pub enum ApiScope {
+    /// Create uploads through `createUpload` or `POST /api/v1/uploads`
+    UploadCreate,
+    /// Pause, resume, skip and configure the slideshows
+    ScreensaverControl,
+    /// Fetch what a display should show from `/api/v1/displays/{display}/current`
+    DevicePull,
+}
+impl ApiScope {
+    pub(crate) fn as_str(&self) -> &str {
+        match self {
+            ApiScope::UploadCreate => "upload:create",
+            ApiScope::ScreensaverControl => "screensaver:control",
+            ApiScope::DevicePull => "device:pull",
+        }
+    }
+}
+
+impl FromStr for ApiScope {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "upload:create" => Ok(ApiScope::UploadCreate),
+            "screensaver:control" => Ok(ApiScope::ScreensaverControl),
+            "device:pull" => Ok(ApiScope::DevicePull),
+            _ => Err(anyhow::anyhow!("Invalid API scope: {}", s)),
+        }
+    }
+}
+
+#[derive(Queryable, Debug, Identifiable, Insertable, Selectable, PartialEq, Eq, Clone)]
+#[diesel(primary_key(uuid), table_name = api_tokens)]
+pub struct ApiToken {
+    pub uuid: UUID,
+    pub user_uuid: UUID,
+    pub name: String,
+    pub token_hash: String,
+    pub scopes: String,
+    pub created_at: NaiveDateTime,
+    pub last_used_at: Option<NaiveDateTime>,
+}
+
+impl ApiToken {
+    /// Scopes of the token, unknown ones are skipped
+    pub fn scope_list(&self) -> Vec<ApiScope> {
+        self.scopes
+            .split(',')
+            .filter_map(|scope| scope.parse().ok())
+            .collect()
+    }
+}
+
+#[juniper::graphql_object(context = GraphQLContext)]
+impl ApiToken {
+    pub fn uuid(&self) -> Uuid {
+        self.uuid.into()
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn scopes(&self) -> Vec<ApiScope> {
+        self.scope_list()
+    }
+    pub fn created_at(&self) -> NaiveDateTime {
+        self.created_at
+    }
+    pub fn last_used_at(&self) -> Option<NaiveDateTime> {
+        self.last_used_at
+    }
+}
+
+/// Kind of thing an audit log entry is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, GraphQLEnum)]
+pub enum AuditTargetType {
+    Upload,
+    User,
+    ApiToken,
+    Album,
+    Playlist,
+    ScheduleRule,
+    /// A display's slideshow, the target id is the display format
+    Display,
+    /// A setting, the target id is its config key
+    Config,
+}
+impl AuditTargetType {
+    pub(crate) fn as_str(&self) -> &str {
+        match self {
+            AuditTargetType::Upload => "upload",
+            AuditTargetType::User => "user",
+            AuditTargetType::ApiToken => "api_token",
+            AuditTargetType::Album => "album",
+            AuditTargetType::Playlist => "playlist",
+            AuditTargetType::ScheduleRule => "schedule_rule",
+            AuditTargetType::Display => "display",
+            AuditTargetType::Config => "config",
+        }
+    }
+}
+
+impl FromStr for AuditTargetType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "upload" => Ok(AuditTargetType::Upload),
+            "user" => Ok(AuditTargetType::User),
+            "api_token" => Ok(AuditTargetType::ApiToken),
+            "album" => Ok(AuditTargetType::Album),
+            "playlist" => Ok(AuditTargetType::Playlist),
+            "schedule_rule" => Ok(AuditTargetType::ScheduleRule),
+            "display" => Ok(AuditTargetType::Display),
+            "config" => Ok(AuditTargetType::Config),
+            _ => Err(anyhow::anyhow!("Invalid audit target type: {}", s)),
         }
     }
 }
+
+/// One change made through the APIs. Values are JSON snapshots of the fields that changed.
+#[derive(Queryable, Debug, Identifiable, Insertable, Selectable, PartialEq, Eq, Clone)]
+#[diesel(primary_key(uuid), table_name = audit_log)]
+pub struct AuditEntry {
+    pub uuid: UUID,
+    pub actor_uuid: Option<UUID>,
+    pub actor_name: Option<String>,
+    pub client_ip: Option<String>,
+    pub action: String,
+    pub target_type: String,
+    pub target_id: String,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[juniper::graphql_object(context = GraphQLContext)]
+impl AuditEntry {
+    pub fn uuid(&self) -> Uuid {
+        self.uuid.into()
+    }
+    pub fn actor_uuid(&self) -> Option<Uuid> {
+        self.actor_uuid.map(Uuid::from)
+    }
+    /// The user who made the change, empty for anonymous requests and deleted users
+    pub fn actor(&self, context: &GraphQLContext) -> Option<User> {
+        self.actor_uuid.and_then(|uuid| UserSvc::get(context, uuid.into()).ok())
+    }
+    /// Username at the time of the change, kept after the user is deleted
+    pub fn actor_name(&self) -> Option<&str> {
+        self.actor_name.as_deref()
+    }
+    pub fn client_ip(&self) -> Option<&str> {
+        self.client_ip.as_deref()
+    }
+    /// Name of the mutation, e.g. `updateUpload`
+    pub fn action(&self) -> &str {
+        &self.action
+    }
+    pub fn target_type(&self) -> Result<AuditTargetType> {
+        self.target_type.parse()
+    }
+    /// UUID, display or config key of the target
+    pub fn target_id(&self) -> &str {
+        &self.target_id
+    }
+    /// JSON of the changed fields before the change
+    pub fn old_value(&self) -> Option<&str> {
+        self.old_value.as_deref()
+    }
+    /// JSON of the changed fields after the change
+    pub fn new_value(&self) -> Option<&str> {
+        self.new_value.as_deref()
+    }
+    pub fn created_at(&self) -> NaiveDateTime {
+        self.created_at
+    }
+}
+
+/// Which audit log entries to list, every filter is optional
+#[derive(GraphQLInputObject, Debug, Clone, Default)]
+pub struct AuditLogFilter {
+    pub actor_uuid: Option<Uuid>,
+    /// Name of the mutation, e.g. `deleteUpload`
+    pub action: Option<String>,
+    pub target_type: Option<AuditTargetType>,
+    pub target_id: Option<String>,
+    /// Only changes made at or after this time
+    pub from: Option<NaiveDateTime>,
+    /// Only changes made before this time
+    pub to: Option<NaiveDateTime>,
+}
+
+/// A link that shows one upload to whoever holds it, until it expires or is revoked
+#[derive(Queryable, Debug, Identifiable, Insertable, Selectable, PartialEq, Eq, Clone)]
+#[diesel(primary_key(uuid), table_name = share_links)]
+pub struct ShareLink {
+    pub uuid: UUID,
+    pub upload_uuid: UUID,
+    pub created_by: Option<UUID>,
+    pub expires_at: NaiveDateTime,
+    pub revoked_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+impl ShareLink {
+    /// Whether the link still shows its upload at `now`
+    pub fn is_active(&self, now: NaiveDateTime) -> bool {
+        self.revoked_at.is_none() && now < self.expires_at
+    }
+}
+
+#[juniper::graphql_object(context = GraphQLContext)]
+impl ShareLink {
+    pub fn uuid(&self) -> Uuid {
+        self.uuid.into()
+    }
+    pub fn upload_uuid(&self) -> Uuid {
+        self.upload_uuid.into()
+    }
+    pub fn created_by(&self) -> Option<Uuid> {
+        self.created_by.map(Uuid::from)
+    }
+    /// Signed URL of the upload's image, anyone with it can see the upload
+    pub async fn url(&self, context: &GraphQLContext) -> Result<String> {
+        ShareSvc::url(context, self).await
+    }
+    pub fn expires_at(&self) -> NaiveDateTime {
+        self.expires_at
+    }
+    pub fn revoked_at(&self) -> Option<NaiveDateTime> {
+        self.revoked_at
+    }
+    /// Whether the link works right now, it is neither expired nor revoked
+    pub fn active(&self) -> bool {
+        self.is_active(chrono::Utc::now().naive_utc())
+    }
+    pub fn created_at(&self) -> NaiveDateTime {
+        self.created_at
+    }
+}
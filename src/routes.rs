@@ -1,23 +1,29 @@
-use crate::api::api_routes;
+use crate::api::{api_routes, authenticate, client_ip};
 use crate::context::GraphQLContext;
 use crate::graphql::{create_schema, Schema};
+use crate::subscriptions;
+use crate::svc::{has_mutation, QueryLimitError, QueryLimits};
+use crate::{get_env, is_production};
 
-use axum::extract::{Request, WebSocketUpgrade};
-use axum::http::{HeaderValue, Uri};
+use axum::extract::{ConnectInfo, Request, WebSocketUpgrade};
+use axum::http::{HeaderMap, HeaderValue, Uri};
 use axum::middleware::{self, Next};
 use axum::response::{IntoResponse, Response};
 use axum::routing::{get, on, MethodFilter};
-use axum::{Extension, Router};
+use axum::{Extension, Json, Router};
+use juniper::http::GraphQLBatchRequest;
 use juniper_axum::extract::JuniperRequest;
 use juniper_axum::response::JuniperResponse;
-use juniper_axum::{graphiql, playground, subscriptions};
+use juniper_axum::{graphiql, playground};
 use juniper_graphql_ws::ConnectionConfig;
 use reqwest::{header, Method, StatusCode};
 use rust_embed::RustEmbed;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use tower::ServiceBuilder;
 use tower_http::compression::CompressionLayer;
 use tower_http::cors::{Any, CorsLayer};
+use tracing::warn;
 
 #[derive(RustEmbed, Clone)]
 #[folder = "site/build/"]
@@ -58,33 +64,33 @@ async fn index_handler() -> impl IntoResponse {
 
 pub fn app(context: GraphQLContext) -> Router {
     let qm_schema = create_schema();
-
-    let cors = CorsLayer::new()
-        .allow_methods([Method::GET])
-        .allow_headers(Any)
-        .allow_methods(Any)
-        .allow_origin(Any);
+    let production = is_production();
 
     let middleware = ServiceBuilder::new()
-        .layer(cors)
+        .layer(cors_layer(production))
         .layer(CompressionLayer::new());
-    let graphql_routes = Router::new()
+    let mut graphql_routes = Router::new()
         .route(
             "/",
             on(MethodFilter::GET.or(MethodFilter::POST), custom_graphql),
         )
-        .route("/subscriptions", get(custom_subscriptions))
-        .route(
-            "/graphiql",
-            get(graphiql("/graphql", "/graphql/subscriptions")),
-        )
-        .route(
-            "/playground",
-            get(playground("/graphql", "/graphql/subscriptions")),
-        )
-        .route("/test", get(root))
+        .route("/subscriptions", get(custom_subscriptions));
+    if !production {
+        graphql_routes = graphql_routes
+            .route(
+                "/graphiql",
+                get(graphiql("/graphql", "/graphql/subscriptions")),
+            )
+            .route(
+                "/playground",
+                get(playground("/graphql", "/graphql/subscriptions")),
+            )
+            .route("/test", get(root));
+    }
+    let graphql_routes = graphql_routes
         .layer(Extension(context.clone()))
-        .layer(Extension(Arc::new(qm_schema)));
+        .layer(Extension(Arc::new(qm_schema)))
+        .layer(Extension(QueryLimits::from_env()));
 
     // let site_router = Router::new()
     //     .nest_service(
@@ -108,6 +114,41 @@ pub fn app(context: GraphQLContext) -> Router {
         .layer(middleware)
 }
 
+/// Cross-origin access for the origins listed in `CORS_ALLOWED_ORIGINS`, separated by commas.
+/// Listed origins may send the session cookie along. Without a list any origin may call the API
+/// during development and none in production.
+fn cors_layer(production: bool) -> CorsLayer {
+    let allowed = get_env("CORS_ALLOWED_ORIGINS", "");
+    let origins: Vec<HeaderValue> = allowed
+        .split(',')
+        .map(str::trim)
+        .filter(|origin| !origin.is_empty())
+        .filter_map(|origin| match HeaderValue::from_str(origin) {
+            Ok(origin) => Some(origin),
+            Err(_) => {
+                warn!("Ignoring invalid CORS origin {:?}", origin);
+                None
+            }
+        })
+        .collect();
+
+    let allow_any = origins.iter().any(|origin| origin == "*");
+    if allow_any || (origins.is_empty() && !production) {
+        return CorsLayer::new()
+            .allow_headers(Any)
+            .allow_methods(Any)
+            .allow_origin(Any);
+    }
+    if origins.is_empty() {
+        return CorsLayer::new();
+    }
+    CorsLayer::new()
+        .allow_origin(origins)
+        .allow_methods([Method::GET, Method::POST])
+        .allow_headers([header::CONTENT_TYPE, header::AUTHORIZATION])
+        .allow_credentials(true)
+}
+
 async fn root() -> &'static str {
     "Hello world!"
 }
@@ -116,13 +157,17 @@ async fn root() -> &'static str {
 async fn custom_subscriptions(
     Extension(schema): Extension<Arc<Schema>>,
     Extension(context): Extension<GraphQLContext>,
+    Extension(query_limits): Extension<QueryLimits>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     ws: WebSocketUpgrade,
 ) -> Response {
+    let context = request_context(&context, &headers, peer);
     ws.protocols(["graphql-transport-ws", "graphql-ws"])
         .on_upgrade(move |socket| {
             let connection_config =
                 ConnectionConfig::new(context.clone()).with_max_in_flight_operations(10);
-            subscriptions::serve_ws(socket, schema, connection_config)
+            subscriptions::serve_ws(socket, schema, connection_config, query_limits)
         })
 }
 
@@ -130,9 +175,75 @@ async fn custom_subscriptions(
 async fn custom_graphql(
     Extension(schema): Extension<Arc<Schema>>,
     Extension(context): Extension<GraphQLContext>,
+    Extension(query_limits): Extension<QueryLimits>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    method: Method,
+    headers: HeaderMap,
     JuniperRequest(request): JuniperRequest,
-) -> JuniperResponse {
-    JuniperResponse(request.execute(&*schema, &context).await)
+) -> Response {
+    // Browsers send the session cookie along on links from other sites, only POST may mutate
+    if method == Method::GET && request_mutates(&schema, &request) {
+        let error = serde_json::json!({
+            "message": "Mutations must be sent with POST",
+            "extensions": { "code": "MUTATION_OVER_GET" },
+        });
+        return (
+            StatusCode::METHOD_NOT_ALLOWED,
+            [(header::ALLOW, "POST")],
+            Json(serde_json::json!({ "errors": [error] })),
+        )
+            .into_response();
+    }
+
+    if let Err(e) = check_query_limits(&query_limits, &schema, &request) {
+        warn!("Rejected GraphQL query: {}", e);
+        let error = serde_json::json!({
+            "message": e.to_string(),
+            "extensions": { "code": e.code() },
+        });
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "errors": [error] })),
+        )
+            .into_response();
+    }
+
+    let context = request_context(&context, &headers, peer);
+    JuniperResponse(request.execute(&*schema, &context).await).into_response()
+}
+
+/// The context of a GraphQL request, for queries and mutations sent over the WebSocket as well so
+/// they count against the per-IP upload limit
+fn request_context(
+    context: &GraphQLContext,
+    headers: &HeaderMap,
+    peer: SocketAddr,
+) -> GraphQLContext {
+    authenticate(context, headers).with_client_ip(client_ip(headers, peer))
+}
+
+/// Measure every query of a request before any of them runs
+fn check_query_limits(
+    query_limits: &QueryLimits,
+    schema: &Schema,
+    request: &GraphQLBatchRequest,
+) -> Result<(), QueryLimitError> {
+    match request {
+        GraphQLBatchRequest::Single(request) => query_limits.check(&request.query, &schema.schema),
+        GraphQLBatchRequest::Batch(requests) => requests
+            .iter()
+            .try_for_each(|request| query_limits.check(&request.query, &schema.schema)),
+    }
+}
+
+/// Whether any query of a request contains a mutation
+fn request_mutates(schema: &Schema, request: &GraphQLBatchRequest) -> bool {
+    match request {
+        GraphQLBatchRequest::Single(request) => has_mutation(&request.query, &schema.schema),
+        GraphQLBatchRequest::Batch(requests) => requests
+            .iter()
+            .any(|request| has_mutation(&request.query, &schema.schema)),
+    }
 }
 
 async fn set_static_cache_control(request: Request, next: Next) -> Response {
@@ -143,3 +254,17 @@ async fn set_static_cache_control(request: Request, next: Next) -> Response {
     );
     response
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_knows_the_client_ip_of_websocket_requests() {
+        let context = GraphQLContext::in_memory();
+        let peer: SocketAddr = "192.0.2.7:40000".parse().unwrap();
+        let context = request_context(&context, &HeaderMap::new(), peer);
+        assert_eq!(context.client_ip, Some(peer.ip()));
+        assert!(context.user.is_none());
+    }
+}
@@ -1,5 +1,48 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    album_uploads (album_uuid, upload_uuid) {
+        album_uuid -> Binary,
+        upload_uuid -> Binary,
+    }
+}
+
+diesel::table! {
+    api_tokens (uuid) {
+        uuid -> Binary,
+        user_uuid -> Binary,
+        name -> Text,
+        token_hash -> Text,
+        scopes -> Text,
+        created_at -> Timestamp,
+        last_used_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    albums (uuid) {
+        uuid -> Binary,
+        name -> Text,
+        description -> Nullable<Text>,
+        created_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    audit_log (uuid) {
+        uuid -> Binary,
+        actor_uuid -> Nullable<Binary>,
+        actor_name -> Nullable<Text>,
+        client_ip -> Nullable<Text>,
+        action -> Text,
+        target_type -> Text,
+        target_id -> Text,
+        old_value -> Nullable<Text>,
+        new_value -> Nullable<Text>,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     config (key) {
         key -> Nullable<Text>,
@@ -7,6 +50,77 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    display_history (uuid) {
+        uuid -> Binary,
+        display -> Text,
+        upload_uuid -> Binary,
+        started_at -> Timestamp,
+        ended_at -> Nullable<Timestamp>,
+        triggered_by -> Text,
+    }
+}
+
+diesel::table! {
+    playlist_items (playlist_uuid, position) {
+        playlist_uuid -> Binary,
+        position -> Integer,
+        upload_uuid -> Binary,
+        duration_seconds -> Nullable<Integer>,
+    }
+}
+
+diesel::table! {
+    playlists (uuid) {
+        uuid -> Binary,
+        name -> Text,
+        created_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    schedule_rules (uuid) {
+        uuid -> Binary,
+        name -> Text,
+        weekdays -> Integer,
+        day_of_month -> Nullable<Integer>,
+        start_time -> Text,
+        end_time -> Text,
+        upload_uuid -> Nullable<Binary>,
+        playlist_uuid -> Nullable<Binary>,
+        priority -> Integer,
+        enabled -> Bool,
+        created_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    sessions (token_hash) {
+        token_hash -> Text,
+        user_uuid -> Binary,
+        created_at -> Timestamp,
+        expires_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    share_links (uuid) {
+        uuid -> Binary,
+        upload_uuid -> Binary,
+        created_by -> Nullable<Binary>,
+        expires_at -> Timestamp,
+        revoked_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    upload_tags (upload_uuid, tag) {
+        upload_uuid -> Binary,
+        tag -> Text,
+    }
+}
+
 diesel::table! {
     uploads (uuid) {
         uuid -> Binary,
@@ -16,7 +130,55 @@ diesel::table! {
         uploaded_at -> Nullable<Timestamp>,
         name -> Nullable<Text>,
         display -> Nullable<Text>,
+        deleted_at -> Nullable<Timestamp>,
+        weight -> Integer,
+        favorite -> Bool,
+        owner_uuid -> Nullable<Binary>,
+        status -> Text,
+        moderated_by -> Nullable<Binary>,
+        moderated_at -> Nullable<Timestamp>,
+        rejection_reason -> Nullable<Text>,
+        content_hash -> Nullable<Text>,
     }
 }
 
-diesel::allow_tables_to_appear_in_same_query!(config, uploads,);
+diesel::table! {
+    users (uuid) {
+        uuid -> Binary,
+        username -> Text,
+        password_hash -> Text,
+        is_admin -> Bool,
+        created_at -> Nullable<Timestamp>,
+        trusted -> Bool,
+        storage_quota_mb -> Nullable<Integer>,
+    }
+}
+
+diesel::joinable!(album_uploads -> albums (album_uuid));
+diesel::joinable!(album_uploads -> uploads (upload_uuid));
+diesel::joinable!(api_tokens -> users (user_uuid));
+diesel::joinable!(playlist_items -> playlists (playlist_uuid));
+diesel::joinable!(playlist_items -> uploads (upload_uuid));
+diesel::joinable!(schedule_rules -> playlists (playlist_uuid));
+diesel::joinable!(schedule_rules -> uploads (upload_uuid));
+diesel::joinable!(sessions -> users (user_uuid));
+diesel::joinable!(share_links -> uploads (upload_uuid));
+diesel::joinable!(upload_tags -> uploads (upload_uuid));
+diesel::joinable!(uploads -> users (owner_uuid));
+
+diesel::allow_tables_to_appear_in_same_query!(
+    album_uploads,
+    albums,
+    api_tokens,
+    audit_log,
+    config,
+    display_history,
+    playlist_items,
+    playlists,
+    schedule_rules,
+    sessions,
+    share_links,
+    upload_tags,
+    uploads,
+    users,
+);
@@ -0,0 +1,172 @@
+//! The `/graphql/subscriptions` WebSocket. Juniper's connections speak both protocols, but every
+//! operation a client starts is measured against the query limits first, like the queries sent to
+//! `/graphql`.
+
+use crate::context::GraphQLContext;
+use crate::graphql::Schema;
+use crate::svc::QueryLimits;
+
+use axum::extract::ws::{self, WebSocket};
+use futures::{channel::mpsc, future, stream, SinkExt, StreamExt};
+use juniper::{DefaultScalarValue, GraphQLError, RuleError};
+use juniper_graphql_ws::graphql_ws::ErrorPayload;
+use juniper_graphql_ws::{graphql_transport_ws, graphql_ws, ConnectionConfig};
+use serde::de::DeserializeOwned;
+use std::sync::Arc;
+use tracing::warn;
+
+type Config = ConnectionConfig<GraphQLContext>;
+
+/// Serve the protocol the client asked for, `graphql-transport-ws` unless it is `graphql-ws`
+pub async fn serve_ws(
+    socket: WebSocket,
+    schema: Arc<Schema>,
+    config: Config,
+    query_limits: QueryLimits,
+) {
+    if socket.protocol().map(AsRef::as_ref) == Some("graphql-ws".as_bytes()) {
+        serve_graphql_ws(socket, schema, config, query_limits).await;
+    } else {
+        serve_graphql_transport_ws(socket, schema, config, query_limits).await;
+    }
+}
+
+async fn serve_graphql_transport_ws(
+    socket: WebSocket,
+    schema: Arc<Schema>,
+    config: Config,
+    query_limits: QueryLimits,
+) {
+    use graphql_transport_ws::{ClientMessage, Input, Output, ServerMessage};
+
+    let (ws_tx, mut ws_rx) = socket.split();
+    let (mut s_tx, s_rx) = graphql_transport_ws::Connection::new(schema.clone(), config).split();
+    let (rejected_tx, rejected_rx) = mpsc::unbounded();
+
+    let input = async move {
+        while let Some(Ok(message)) = ws_rx.next().await {
+            let input = match read_frame::<ClientMessage<DefaultScalarValue>>(message) {
+                Some(Frame::Message(message)) => message.map(Input::Message),
+                Some(Frame::Close) => Ok(Input::Close),
+                None => continue,
+            };
+            if let Ok(Input::Message(ClientMessage::Subscribe { id, payload })) = &input {
+                if let Err(payload) = check_operation(&query_limits, &schema, &payload.query) {
+                    let id = id.clone();
+                    _ = rejected_tx
+                        .unbounded_send(Output::Message(ServerMessage::Error { id, payload }));
+                    continue;
+                }
+            }
+            _ = s_tx.send(Decoded(input)).await;
+        }
+    };
+
+    let output = stream::select(s_rx, rejected_rx)
+        .map(|output| {
+            Ok(match output {
+                Output::Message(message) => to_text(&message),
+                Output::Close { code, message } => ws::Message::Close(Some(ws::CloseFrame {
+                    code,
+                    reason: message.into(),
+                })),
+            })
+        })
+        .forward(ws_tx);
+
+    _ = future::select(Box::pin(input), output).await;
+}
+
+async fn serve_graphql_ws(
+    socket: WebSocket,
+    schema: Arc<Schema>,
+    config: Config,
+    query_limits: QueryLimits,
+) {
+    use graphql_ws::{ClientMessage, ServerMessage};
+
+    let (ws_tx, mut ws_rx) = socket.split();
+    let (mut s_tx, s_rx) = graphql_ws::Connection::new(schema.clone(), config).split();
+    let (rejected_tx, rejected_rx) = mpsc::unbounded();
+
+    let input = async move {
+        while let Some(Ok(message)) = ws_rx.next().await {
+            let message = match read_frame::<ClientMessage<DefaultScalarValue>>(message) {
+                Some(Frame::Message(message)) => message,
+                Some(Frame::Close) => Ok(ClientMessage::ConnectionTerminate),
+                None => continue,
+            };
+            if let Ok(ClientMessage::Start { id, payload }) = &message {
+                if let Err(payload) = check_operation(&query_limits, &schema, &payload.query) {
+                    let id = id.clone();
+                    _ = rejected_tx.unbounded_send(ServerMessage::Error { id, payload });
+                    continue;
+                }
+            }
+            _ = s_tx.send(Decoded(message)).await;
+        }
+    };
+
+    let output = stream::select(s_rx, rejected_rx)
+        .map(|message| Ok(to_text(&message)))
+        .forward(ws_tx);
+
+    _ = future::select(Box::pin(input), output).await;
+}
+
+/// Check an operation before Juniper starts it, the error is what the client is sent instead
+fn check_operation(
+    query_limits: &QueryLimits,
+    schema: &Schema,
+    query: &str,
+) -> Result<(), ErrorPayload> {
+    query_limits.check(query, &schema.schema).map_err(|e| {
+        warn!("Rejected GraphQL subscription: {}", e);
+        GraphQLError::ValidationError(vec![RuleError::new(&e.to_string(), &[])]).into()
+    })
+}
+
+enum Frame<T> {
+    Message(Result<T, serde_json::Error>),
+    Close,
+}
+
+/// Read a client message off the socket. Pings and pongs are left to axum.
+fn read_frame<T: DeserializeOwned>(message: ws::Message) -> Option<Frame<T>> {
+    match message {
+        ws::Message::Text(text) => Some(Frame::Message(serde_json::from_str(text.as_str()))),
+        ws::Message::Binary(bytes) => Some(Frame::Message(serde_json::from_slice(&bytes))),
+        ws::Message::Close(_) => Some(Frame::Close),
+        ws::Message::Ping(_) | ws::Message::Pong(_) => None,
+    }
+}
+
+fn to_text(message: &impl serde::Serialize) -> ws::Message {
+    serde_json::to_string(message)
+        .map(ws::Message::text)
+        .unwrap_or_else(|e| {
+            ws::Message::Close(Some(ws::CloseFrame {
+                code: 1011,
+                reason: format!("error serializing response: {e}").into(),
+            }))
+        })
+}
+
+/// A decoded client message for Juniper, which answers messages that weren't valid JSON itself
+struct Decoded<T>(Result<T, serde_json::Error>);
+
+impl TryFrom<Decoded<Self>> for graphql_transport_ws::Input<DefaultScalarValue> {
+    type Error = serde_json::Error;
+
+    fn try_from(decoded: Decoded<Self>) -> Result<Self, Self::Error> {
+        decoded.0
+    }
+}
+
+impl TryFrom<Decoded<Self>> for graphql_ws::ClientMessage<DefaultScalarValue> {
+    type Error = serde_json::Error;
+
+    fn try_from(decoded: Decoded<Self>) -> Result<Self, Self::Error> {
+        decoded.0
+    }
+}
@@ -0,0 +1,117 @@
+use crate::{
+    context::GraphQLContext,
+    db::get_conn,
+    models::{Album, AlbumUpload, Upload},
+    schema::{album_uploads, albums, uploads},
+    svc::UploadSvc,
+    uuid::UUID,
+};
+use anyhow::{Context, Result};
+use diesel::prelude::*;
+use uuid::Uuid;
+
+pub struct AlbumSvc {}
+
+impl AlbumSvc {
+    pub fn get(context: &GraphQLContext, album_uuid: Uuid) -> Result<Album> {
+        albums::table
+            .filter(albums::uuid.eq(UUID::from(&album_uuid)))
+            .first(&mut get_conn(context))
+            .context("Could not find album")
+    }
+    pub fn list(context: &GraphQLContext) -> Result<Vec<Album>> {
+        albums::table
+            .order_by(albums::name.asc())
+            .load::<Album>(&mut get_conn(context))
+            .context("Could not load albums")
+    }
+    pub fn create(context: &GraphQLContext, name: &str, description: Option<String>) -> Result<Album> {
+        let album = Album {
+            uuid: UUID::random(),
+            name: name.trim().to_owned(),
+            description,
+            created_at: Some(chrono::Utc::now().naive_utc()),
+        };
+        if album.name.is_empty() {
+            anyhow::bail!("Album name cannot be empty");
+        }
+
+        diesel::insert_into(albums::table)
+            .values(&album)
+            .execute(&mut get_conn(context))
+            .context("Could not create album")?;
+
+        Self::get(context, album.uuid.into())
+    }
+    pub async fn delete(context: &GraphQLContext, album_uuid: Uuid) -> Result<()> {
+        let mut conn = get_conn(context);
+        conn.transaction(|conn| {
+            diesel::delete(album_uploads::table)
+                .filter(album_uploads::album_uuid.eq(UUID::from(&album_uuid)))
+                .execute(conn)?;
+            diesel::delete(albums::table)
+                .filter(albums::uuid.eq(UUID::from(&album_uuid)))
+                .execute(conn)
+        })
+        .context("Could not delete album")?;
+
+        // Stop restricting the screensaver to an album that no longer exists
+        for screensaver in context.screensavers() {
+            if screensaver.get_state().await.album == Some(album_uuid) {
+                screensaver.set_album(None).await?;
+            }
+        }
+
+        Ok(())
+    }
+    pub fn add_uploads(
+        context: &GraphQLContext,
+        album_uuid: Uuid,
+        upload_uuids: &[Uuid],
+    ) -> Result<Album> {
+        let album = Self::get(context, album_uuid)?;
+        for upload_uuid in upload_uuids {
+            UploadSvc::get_visible(context, *upload_uuid)?;
+        }
+        let entries: Vec<AlbumUpload> = upload_uuids
+            .iter()
+            .map(|upload_uuid| AlbumUpload {
+                album_uuid: album.uuid,
+                upload_uuid: UUID::from(upload_uuid),
+            })
+            .collect();
+        if entries.is_empty() {
+            return Ok(album);
+        }
+
+        diesel::insert_or_ignore_into(album_uploads::table)
+            .values(&entries)
+            .execute(&mut get_conn(context))
+            .context("Could not add uploads to album")?;
+
+        Ok(album)
+    }
+    pub fn remove_upload(
+        context: &GraphQLContext,
+        album_uuid: Uuid,
+        upload_uuid: Uuid,
+    ) -> Result<Album> {
+        diesel::delete(album_uploads::table)
+            .filter(album_uploads::album_uuid.eq(UUID::from(&album_uuid)))
+            .filter(album_uploads::upload_uuid.eq(UUID::from(&upload_uuid)))
+            .execute(&mut get_conn(context))
+            .context("Could not remove upload from album")?;
+
+        Self::get(context, album_uuid)
+    }
+    pub fn list_uploads(context: &GraphQLContext, album_uuid: Uuid) -> Result<Vec<Upload>> {
+        uploads::table
+            .inner_join(album_uploads::table)
+            .filter(album_uploads::album_uuid.eq(UUID::from(&album_uuid)))
+            .filter(uploads::deleted_at.is_null())
+            .select(Upload::as_select())
+            .order_by(uploads::uploaded_at.desc())
+            .load::<Upload>(&mut get_conn(context))
+            .context("Could not load album uploads")
+    }
+}
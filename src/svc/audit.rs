@@ -0,0 +1,185 @@
+use crate::{
+    context::GraphQLContext,
+    db::get_conn,
+    models::{
+        ApiToken, AuditEntry, AuditLogFilter, AuditTargetType, QuietHours, ScheduleRule, ShareLink,
+        Upload, User,
+    },
+    schema::audit_log,
+    svc::ScheduleSvc,
+    uuid::UUID,
+};
+use anyhow::{Context, Result};
+use diesel::prelude::*;
+use serde_json::{json, Value};
+use tracing::error;
+use uuid::Uuid;
+
+pub struct AuditSvc {}
+
+impl AuditSvc {
+    /// Log a change made by the context's user. The change already went through, so a failure to
+    /// log it is reported but doesn't fail the request.
+    pub fn record(
+        context: &GraphQLContext,
+        action: &str,
+        target_type: AuditTargetType,
+        target_id: impl ToString,
+        old_value: Option<Value>,
+        new_value: Option<Value>,
+    ) {
+        let entry = AuditEntry {
+            uuid: UUID::random(),
+            actor_uuid: context.user.as_ref().map(|user| user.uuid),
+            actor_name: context.user.as_ref().map(|user| user.username.clone()),
+            client_ip: context.client_ip.map(|ip| ip.to_string()),
+            action: action.to_owned(),
+            target_type: target_type.as_str().to_owned(),
+            target_id: target_id.to_string(),
+            old_value: old_value.map(|value| value.to_string()),
+            new_value: new_value.map(|value| value.to_string()),
+            created_at: chrono::Utc::now().naive_utc(),
+        };
+        if let Err(e) = diesel::insert_into(audit_log::table)
+            .values(&entry)
+            .execute(&mut get_conn(context))
+        {
+            error!("Could not record {} in the audit log: {}", action, e);
+        }
+    }
+
+    /// Entries matching the filter, newest first
+    pub fn list(
+        context: &GraphQLContext,
+        filter: &AuditLogFilter,
+        limit: i32,
+        offset: i32,
+    ) -> Result<Vec<AuditEntry>> {
+        let limit: i64 = limit.into();
+        let offset: i64 = offset.into();
+
+        let mut query = audit_log::table.into_boxed();
+        if let Some(actor_uuid) = filter.actor_uuid {
+            query = query.filter(audit_log::actor_uuid.eq(UUID::from(&actor_uuid)));
+        }
+        if let Some(action) = &filter.action {
+            query = query.filter(audit_log::action.eq(action.clone()));
+        }
+        if let Some(target_type) = filter.target_type {
+            query = query.filter(audit_log::target_type.eq(target_type.as_str().to_owned()));
+        }
+        if let Some(target_id) = &filter.target_id {
+            query = query.filter(audit_log::target_id.eq(target_id.clone()));
+        }
+        if let Some(from) = filter.from {
+            query = query.filter(audit_log::created_at.ge(from));
+        }
+        if let Some(to) = filter.to {
+            query = query.filter(audit_log::created_at.lt(to));
+        }
+
+        query
+            .order_by(audit_log::created_at.desc())
+            .limit(limit)
+            .offset(offset)
+            .load::<AuditEntry>(&mut get_conn(context))
+            .context("Could not load audit log")
+    }
+}
+
+/// What the audit log keeps of an upload, the data itself only by its hash
+pub fn upload_snapshot(upload: &Upload) -> Value {
+    json!({
+        "name": upload.name,
+        "message": upload.message,
+        "display": upload.display,
+        "public": upload.public,
+        "weight": upload.weight,
+        "favorite": upload.favorite,
+        "status": upload.status,
+        "ownerUuid": upload.owner_uuid.map(Uuid::from),
+        "contentHash": upload.content_hash,
+    })
+}
+
+/// What the audit log keeps of a user, never the password hash
+pub fn user_snapshot(user: &User) -> Value {
+    json!({
+        "username": user.username,
+        "isAdmin": user.is_admin,
+        "trusted": user.trusted,
+        "storageQuotaMb": user.storage_quota_mb,
+    })
+}
+
+/// What the audit log keeps of an API token, never the secret
+pub fn api_token_snapshot(api_token: &ApiToken) -> Value {
+    json!({
+        "name": api_token.name,
+        "userUuid": Uuid::from(api_token.user_uuid),
+        "scopes": api_token.scopes,
+    })
+}
+
+pub fn schedule_rule_snapshot(rule: &ScheduleRule) -> Value {
+    let weekdays: Vec<String> = ScheduleSvc::mask_to_weekdays(rule.weekdays)
+        .iter()
+        .map(|weekday| weekday.as_str().to_owned())
+        .collect();
+    json!({
+        "name": rule.name,
+        "weekdays": weekdays,
+        "dayOfMonth": rule.day_of_month,
+        "startTime": rule.start_time,
+        "endTime": rule.end_time,
+        "uploadUuid": rule.upload_uuid.map(Uuid::from),
+        "playlistUuid": rule.playlist_uuid.map(Uuid::from),
+        "priority": rule.priority,
+        "enabled": rule.enabled,
+    })
+}
+
+/// What the audit log keeps of a share link, the signed URL can be made again from it
+pub fn share_link_snapshot(link: &ShareLink) -> Value {
+    json!({
+        "linkUuid": Uuid::from(link.uuid),
+        "expiresAt": link.expires_at.to_string(),
+        "revokedAt": link.revoked_at.map(|at| at.to_string()),
+    })
+}
+
+pub fn quiet_hours_snapshot(quiet_hours: &[QuietHours]) -> Value {
+    quiet_hours
+        .iter()
+        .map(|hours| {
+            json!({
+                "weekday": hours.weekday.as_str(),
+                "startTime": hours.start_time,
+                "endTime": hours.end_time,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_leaves_secrets_out_of_user_snapshots() {
+        let user = User {
+            uuid: UUID::random(),
+            username: "alice".to_owned(),
+            password_hash: "$argon2id$secret".to_owned(),
+            is_admin: true,
+            created_at: None,
+            trusted: false,
+            storage_quota_mb: Some(50),
+        };
+
+        let snapshot = user_snapshot(&user);
+        assert_eq!(snapshot["username"], "alice");
+        assert_eq!(snapshot["storageQuotaMb"], 50);
+        assert!(!snapshot.to_string().contains("argon2"));
+    }
+}
@@ -0,0 +1,155 @@
+use crate::{
+    config::ConfigSvc,
+    context::GraphQLContext,
+    db::get_conn,
+    models::{DuplicatePolicy, Upload},
+    schema::uploads,
+    svc::{FieldViolation, UploadSvc, ValidationError},
+};
+use anyhow::{Context, Result};
+use diesel::prelude::*;
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+
+/// How many uploads get their hash filled in per query when backfilling
+const BACKFILL_BATCH: i64 = 100;
+
+pub struct DuplicateSvc {}
+
+impl DuplicateSvc {
+    /// Apply the duplicate policy to an upload about to be stored. `Some` is the existing upload
+    /// to use instead of storing a new one.
+    pub async fn check(context: &GraphQLContext, upload: &Upload) -> Result<Option<Upload>> {
+        let Some(content_hash) = &upload.content_hash else {
+            return Ok(None);
+        };
+        let Some(existing) = Self::list_with_hash(context, content_hash)?
+            .into_iter()
+            .next()
+        else {
+            return Ok(None);
+        };
+
+        let policy = ConfigSvc::get_duplicate_policy(context)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to load duplicate policy: {}", e))?;
+        // Don't give away private uploads of other users
+        let visible = existing.visible_to(context.user.as_ref());
+        match policy {
+            DuplicatePolicy::Reject => {
+                let message = if visible {
+                    format!("Identical to upload {}", uuid::Uuid::from(existing.uuid))
+                } else {
+                    "An identical upload already exists".to_owned()
+                };
+                Err(ValidationError(vec![FieldViolation::new("data", message)]).into())
+            }
+            DuplicatePolicy::Link if visible => {
+                info!(
+                    "Linking duplicate upload to {:?}",
+                    existing.name.as_deref().unwrap_or("Untitled")
+                );
+                if existing.is_approved() {
+                    UploadSvc::show_new(context, &existing).await?;
+                }
+                Ok(Some(existing))
+            }
+            DuplicatePolicy::Warn | DuplicatePolicy::Link => {
+                warn!(
+                    "Storing duplicate of upload {}",
+                    uuid::Uuid::from(existing.uuid)
+                );
+                Ok(None)
+            }
+        }
+    }
+
+    /// Uploads outside the trash with the given content hash, oldest first
+    pub fn list_with_hash(context: &GraphQLContext, content_hash: &str) -> Result<Vec<Upload>> {
+        uploads::table
+            .filter(uploads::content_hash.eq(content_hash))
+            .filter(uploads::deleted_at.is_null())
+            .select(Upload::as_select())
+            .order_by(uploads::uploaded_at.asc())
+            .load::<Upload>(&mut get_conn(context))
+            .context("Could not load uploads")
+    }
+
+    /// Groups of identical uploads outside the trash, the largest groups first
+    pub fn groups(
+        context: &GraphQLContext,
+        limit: i32,
+        offset: i32,
+    ) -> Result<Vec<(String, Vec<Upload>)>> {
+        let limit: i64 = limit.into();
+        let offset: i64 = offset.into();
+
+        let hashes: Vec<Option<String>> = uploads::table
+            .filter(uploads::content_hash.is_not_null())
+            .filter(uploads::deleted_at.is_null())
+            .group_by(uploads::content_hash)
+            .having(diesel::dsl::count_star().gt(1))
+            .select(uploads::content_hash)
+            .order_by((diesel::dsl::count_star().desc(), uploads::content_hash))
+            .limit(limit)
+            .offset(offset)
+            .load(&mut get_conn(context))
+            .context("Could not load duplicate groups")?;
+
+        hashes
+            .into_iter()
+            .flatten()
+            .map(|content_hash| {
+                let uploads = Self::list_with_hash(context, &content_hash)?;
+                Ok((content_hash, uploads))
+            })
+            .collect()
+    }
+
+    /// Hash uploads stored before content hashes existed, returns how many were updated
+    pub fn backfill(context: &GraphQLContext) -> Result<usize> {
+        let mut updated = 0;
+        loop {
+            let batch: Vec<Upload> = uploads::table
+                .filter(uploads::content_hash.is_null())
+                .select(Upload::as_select())
+                .limit(BACKFILL_BATCH)
+                .load(&mut get_conn(context))
+                .context("Could not load uploads to hash")?;
+            if batch.is_empty() {
+                return Ok(updated);
+            }
+
+            for upload in &batch {
+                diesel::update(uploads::table)
+                    .filter(uploads::uuid.eq(upload.uuid))
+                    .set(uploads::content_hash.eq(content_hash(&upload.data)))
+                    .execute(&mut get_conn(context))
+                    .context("Could not store content hash")?;
+            }
+            updated += batch.len();
+        }
+    }
+}
+
+/// Hex SHA-256 of upload data
+pub fn content_hash(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_hashes_content() {
+        assert_eq!(
+            content_hash(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_ne!(content_hash(&[0; 1024]), content_hash(&[1; 1024]));
+    }
+}
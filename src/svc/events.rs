@@ -0,0 +1,75 @@
+use crate::models::{DeviceStatus, DisplayFormat, ScreensaverChange, Upload};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::broadcast;
+
+/// How many events a slow subscriber may fall behind before it starts missing them
+const EVENT_CAPACITY: usize = 64;
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    UploadCreated(Box<Upload>),
+    ScreensaverChanged(ScreensaverChange),
+    DeviceStatusChanged(DeviceStatus),
+}
+
+/// In-process broadcast channel backing the GraphQL subscriptions
+#[derive(Clone)]
+pub struct EventBus {
+    tx: broadcast::Sender<Event>,
+    /// Last known status of each display, to only publish actual changes
+    device_status: Arc<Mutex<HashMap<DisplayFormat, DeviceStatus>>>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        let (tx, _) = broadcast::channel(EVENT_CAPACITY);
+        Self {
+            tx,
+            device_status: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+}
+
+impl EventBus {
+    /// Publish an event, it is dropped when nobody is subscribed
+    pub fn publish(&self, event: Event) {
+        let _ = self.tx.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.tx.subscribe()
+    }
+
+    /// Record the outcome of a push to a display, publishing when it went online or offline
+    pub fn record_device_push(&self, display: DisplayFormat, result: &anyhow::Result<()>) {
+        let status = DeviceStatus {
+            display,
+            online: result.is_ok(),
+            error: result.as_ref().err().map(|e| format!("{e:#}")),
+            checked_at: chrono::Utc::now().naive_utc(),
+        };
+
+        let changed = {
+            let mut device_status = self.device_status.lock().unwrap_or_else(|e| e.into_inner());
+            let changed = device_status
+                .get(&display)
+                .is_none_or(|previous| previous.online != status.online);
+            device_status.insert(display, status.clone());
+            changed
+        };
+        if changed {
+            self.publish(Event::DeviceStatusChanged(status));
+        }
+    }
+
+    /// Last known status of every display that has been pushed to
+    pub fn device_statuses(&self) -> Vec<DeviceStatus> {
+        let device_status = self.device_status.lock().unwrap_or_else(|e| e.into_inner());
+        let mut statuses: Vec<DeviceStatus> = device_status.values().cloned().collect();
+        statuses.sort_by_key(|status| status.display.as_str().to_owned());
+        statuses
+    }
+}
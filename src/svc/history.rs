@@ -0,0 +1,230 @@
+use crate::{
+    context::GraphQLContext,
+    db::get_conn,
+    models::{DisplayFormat, DisplayHistory, DisplayTrigger, Upload},
+    schema::display_history,
+    svc::UploadSvc,
+    uuid::UUID,
+};
+use anyhow::{Context, Result};
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Most entries one page of history can hold
+pub const MAX_HISTORY_LIMIT: i32 = 500;
+
+pub struct HistorySvc {}
+
+impl HistorySvc {
+    /// Log that `upload` went on screen, ending whatever the display showed before
+    pub fn record(
+        context: &GraphQLContext,
+        display: DisplayFormat,
+        upload: UUID,
+        trigger: DisplayTrigger,
+        at: NaiveDateTime,
+    ) -> Result<()> {
+        Self::close(context, display, at)?;
+        diesel::insert_into(display_history::table)
+            .values(&DisplayHistory {
+                uuid: UUID::random(),
+                display: display.as_str().to_owned(),
+                upload_uuid: upload,
+                started_at: at,
+                ended_at: None,
+                triggered_by: trigger.as_str().to_owned(),
+            })
+            .execute(&mut get_conn(context))
+            .context("Could not record display history")?;
+        Ok(())
+    }
+
+    /// Log that the display stopped showing its slide, because it went dark or offline
+    pub fn close(
+        context: &GraphQLContext,
+        display: DisplayFormat,
+        at: NaiveDateTime,
+    ) -> Result<()> {
+        diesel::update(display_history::table)
+            .filter(display_history::display.eq(display.as_str()))
+            .filter(display_history::ended_at.is_null())
+            .set(display_history::ended_at.eq(at))
+            .execute(&mut get_conn(context))
+            .context("Could not close display history")?;
+        Ok(())
+    }
+
+    /// Entries overlapping `from`..`to`, newest first. `limit` is kept to 1 to
+    /// `MAX_HISTORY_LIMIT`, SQLite would return everything for a negative one.
+    pub fn list(
+        context: &GraphQLContext,
+        display: Option<DisplayFormat>,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+        limit: i32,
+        offset: i32,
+    ) -> Result<Vec<DisplayHistory>> {
+        let limit: i64 = limit.clamp(1, MAX_HISTORY_LIMIT).into();
+        let offset: i64 = offset.max(0).into();
+
+        Self::overlapping(display, from, to)
+            .order_by(display_history::started_at.desc())
+            .limit(limit)
+            .offset(offset)
+            .load::<DisplayHistory>(&mut get_conn(context))
+            .context("Could not load display history")
+    }
+
+    /// What the display showed at `at`, if anything
+    pub fn at(
+        context: &GraphQLContext,
+        display: DisplayFormat,
+        at: NaiveDateTime,
+    ) -> Result<Option<DisplayHistory>> {
+        display_history::table
+            .filter(display_history::display.eq(display.as_str()))
+            .filter(display_history::started_at.le(at))
+            .filter(
+                display_history::ended_at
+                    .is_null()
+                    .or(display_history::ended_at.gt(at)),
+            )
+            .order_by(display_history::started_at.desc())
+            .first::<DisplayHistory>(&mut get_conn(context))
+            .optional()
+            .context("Could not load display history")
+    }
+
+    /// Total seconds each upload was on screen between `from` and `to`, longest first
+    pub fn display_times(
+        context: &GraphQLContext,
+        display: Option<DisplayFormat>,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+    ) -> Result<Vec<(UUID, i64)>> {
+        let entries = Self::overlapping(display, from, to)
+            .load::<DisplayHistory>(&mut get_conn(context))
+            .context("Could not load display history")?;
+        Ok(display_seconds(
+            &entries,
+            from,
+            to,
+            chrono::Utc::now().naive_utc(),
+        ))
+    }
+
+    /// What the viewer may know about the upload of an entry, also when it is in the trash. Its
+    /// id is hidden along with it when it is private or not approved, purged uploads only leave
+    /// their id.
+    pub fn upload(context: &GraphQLContext, upload: UUID) -> (Option<Uuid>, Option<Upload>) {
+        let found = UploadSvc::get(context, upload.into())
+            .or_else(|_| UploadSvc::get_trashed(context, upload.into()))
+            .ok();
+        match found {
+            Some(found) if !found.visible_to(context.user.as_ref()) => (None, None),
+            found => (Some(upload.into()), found),
+        }
+    }
+
+    fn overlapping<'a>(
+        display: Option<DisplayFormat>,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+    ) -> display_history::BoxedQuery<'a, diesel::sqlite::Sqlite> {
+        let mut query = display_history::table.into_boxed();
+        if let Some(display) = display {
+            query = query.filter(display_history::display.eq(display.as_str().to_owned()));
+        }
+        if let Some(from) = from {
+            query = query.filter(
+                display_history::ended_at
+                    .is_null()
+                    .or(display_history::ended_at.gt(from)),
+            );
+        }
+        if let Some(to) = to {
+            query = query.filter(display_history::started_at.lt(to));
+        }
+        query
+    }
+}
+
+/// Sum up screen time per upload, clipping entries to `from`..`to`. Entries still on screen
+/// count until `now`.
+pub fn display_seconds(
+    entries: &[DisplayHistory],
+    from: Option<NaiveDateTime>,
+    to: Option<NaiveDateTime>,
+    now: NaiveDateTime,
+) -> Vec<(UUID, i64)> {
+    let mut totals: HashMap<UUID, i64> = HashMap::new();
+    for entry in entries {
+        let start = from.map_or(entry.started_at, |from| entry.started_at.max(from));
+        let end = entry.ended_at.unwrap_or(now);
+        let end = to.map_or(end, |to| end.min(to));
+        let seconds = (end - start).num_seconds();
+        if seconds > 0 {
+            *totals.entry(entry.upload_uuid).or_default() += seconds;
+        }
+    }
+
+    let mut totals: Vec<(UUID, i64)> = totals.into_iter().collect();
+    totals.sort_by_key(|(_, seconds)| std::cmp::Reverse(*seconds));
+    totals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 2, 20)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    fn entry(upload: UUID, start: NaiveDateTime, end: Option<NaiveDateTime>) -> DisplayHistory {
+        DisplayHistory {
+            uuid: UUID::random(),
+            display: DisplayFormat::RGB320x240.as_str().to_owned(),
+            upload_uuid: upload,
+            started_at: start,
+            ended_at: end,
+            triggered_by: DisplayTrigger::Timer.as_str().to_owned(),
+        }
+    }
+
+    #[test]
+    fn it_sums_screen_time_per_upload() {
+        let a = UUID::random();
+        let b = UUID::random();
+        let entries = [
+            entry(a, at(10, 0), Some(at(10, 2))),
+            entry(b, at(10, 2), Some(at(10, 3))),
+            entry(a, at(10, 3), Some(at(10, 5))),
+        ];
+
+        let totals = display_seconds(&entries, None, None, at(12, 0));
+        assert_eq!(totals, vec![(a, 240), (b, 60)]);
+    }
+
+    #[test]
+    fn it_clips_to_the_range_and_counts_open_entries_until_now() {
+        let a = UUID::random();
+        let b = UUID::random();
+        let entries = [
+            entry(a, at(9, 58), Some(at(10, 1))),
+            entry(b, at(10, 1), None),
+        ];
+
+        let totals = display_seconds(&entries, Some(at(10, 0)), None, at(10, 3));
+        assert_eq!(totals, vec![(b, 120), (a, 60)]);
+
+        let totals = display_seconds(&entries, Some(at(9, 59)), Some(at(10, 2)), at(10, 3));
+        assert_eq!(totals, vec![(a, 120), (b, 60)]);
+    }
+}
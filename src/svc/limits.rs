@@ -0,0 +1,245 @@
+use crate::{
+    config::ConfigSvc,
+    context::GraphQLContext,
+    db::get_conn,
+    schema::{uploads, users},
+    uuid::UUID,
+};
+use anyhow::{Context, Result};
+use diesel::{
+    dsl::sql,
+    prelude::*,
+    sql_types::{BigInt, Nullable},
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use uuid::Uuid;
+
+pub const BYTES_PER_MB: u64 = 1024 * 1024;
+
+/// Span the per minute upload limits count over
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Why a request was turned away, the APIs turn this into a 429 or a GraphQL error with a code
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LimitError {
+    /// Too many uploads in the last minute, try again after `retry_after`
+    RateLimited { retry_after: Duration },
+    /// The upload would take the user past their storage quota
+    QuotaExceeded { used_bytes: u64, quota_bytes: u64 },
+}
+
+impl fmt::Display for LimitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitError::RateLimited { retry_after } => write!(
+                f,
+                "Too many uploads, try again in {} seconds",
+                retry_after.as_secs().max(1)
+            ),
+            LimitError::QuotaExceeded {
+                used_bytes,
+                quota_bytes,
+            } => write!(
+                f,
+                "Storage quota exceeded, {:.1} of {} MB used",
+                *used_bytes as f64 / BYTES_PER_MB as f64,
+                quota_bytes / BYTES_PER_MB
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LimitError {}
+
+/// Sliding window counters of recent requests, keyed by client IP or user
+#[derive(Clone, Default)]
+pub struct RateLimiter {
+    hits: Arc<Mutex<HashMap<String, VecDeque<Instant>>>>,
+}
+
+impl RateLimiter {
+    /// Count a request for `key` unless it already made `limit` requests within the window
+    pub fn check(&self, key: &str, limit: u32, now: Instant) -> Result<(), LimitError> {
+        let mut hits = self.hits.lock().unwrap_or_else(|e| e.into_inner());
+        // Forget clients that went quiet so the map doesn't grow forever
+        hits.retain(|_, times| {
+            while times
+                .front()
+                .is_some_and(|time| now.duration_since(*time) >= RATE_WINDOW)
+            {
+                times.pop_front();
+            }
+            !times.is_empty()
+        });
+
+        let times = hits.entry(key.to_owned()).or_default();
+        if times.len() >= limit as usize {
+            let oldest = times.front().copied().unwrap_or(now);
+            return Err(LimitError::RateLimited {
+                retry_after: RATE_WINDOW.saturating_sub(now.duration_since(oldest)),
+            });
+        }
+        times.push_back(now);
+        Ok(())
+    }
+}
+
+pub struct LimitSvc {}
+
+impl LimitSvc {
+    /// Store whichever of the limits are given, 0 turns a limit off
+    pub async fn set(
+        context: &GraphQLContext,
+        uploads_per_minute_ip: Option<i32>,
+        uploads_per_minute_user: Option<i32>,
+        storage_quota_mb: Option<i32>,
+    ) -> Result<()> {
+        let limits = [
+            uploads_per_minute_ip,
+            uploads_per_minute_user,
+            storage_quota_mb,
+        ];
+        if limits.iter().flatten().any(|limit| *limit < 0) {
+            anyhow::bail!("Limits can't be negative, use 0 for no limit");
+        }
+
+        if let Some(limit) = uploads_per_minute_ip {
+            ConfigSvc::set_uploads_per_minute_ip(context, limit as u32)
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to set upload limit: {}", e))?;
+        }
+        if let Some(limit) = uploads_per_minute_user {
+            ConfigSvc::set_uploads_per_minute_user(context, limit as u32)
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to set upload limit: {}", e))?;
+        }
+        if let Some(quota_mb) = storage_quota_mb {
+            ConfigSvc::set_storage_quota_mb(context, quota_mb as u64)
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to set storage quota: {}", e))?;
+        }
+        Ok(())
+    }
+
+    /// Check the rate limits and storage quota before storing an upload of `size` bytes
+    pub async fn check_upload(context: &GraphQLContext, size: usize) -> Result<()> {
+        let now = Instant::now();
+        if let Some(ip) = context.client_ip {
+            let limit = ConfigSvc::get_uploads_per_minute_ip(context)
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to load upload limit: {}", e))?;
+            if limit > 0 {
+                context
+                    .rate_limiter
+                    .check(&format!("ip:{ip}"), limit, now)?;
+            }
+        }
+
+        let Some(user) = &context.user else {
+            return Ok(());
+        };
+        let limit = ConfigSvc::get_uploads_per_minute_user(context)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to load upload limit: {}", e))?;
+        if limit > 0 {
+            context
+                .rate_limiter
+                .check(&format!("user:{}", user.uuid.0), limit, now)?;
+        }
+
+        if user.is_admin {
+            return Ok(());
+        }
+        let quota_mb = match user.storage_quota_mb {
+            Some(quota_mb) => quota_mb.max(0) as u64,
+            None => ConfigSvc::get_storage_quota_mb(context)
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to load storage quota: {}", e))?,
+        };
+        if quota_mb > 0 {
+            let used_bytes = Self::storage_used(context, user.uuid.into())?;
+            let quota_bytes = quota_mb * BYTES_PER_MB;
+            if used_bytes + size as u64 > quota_bytes {
+                return Err(LimitError::QuotaExceeded {
+                    used_bytes,
+                    quota_bytes,
+                }
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Bytes of upload data the user owns, including uploads in the trash
+    pub fn storage_used(context: &GraphQLContext, user_uuid: Uuid) -> Result<u64> {
+        let bytes: Option<i64> = uploads::table
+            .filter(uploads::owner_uuid.eq(UUID::from(&user_uuid)))
+            .select(sql::<Nullable<BigInt>>("SUM(LENGTH(data))"))
+            .first(&mut get_conn(context))
+            .context("Could not load storage use")?;
+        Ok(bytes.unwrap_or(0).max(0) as u64)
+    }
+
+    /// Give a user their own storage quota, `None` goes back to the default
+    pub fn set_user_quota(
+        context: &GraphQLContext,
+        user_uuid: Uuid,
+        quota_mb: Option<i32>,
+    ) -> Result<()> {
+        if quota_mb.is_some_and(|quota_mb| quota_mb < 0) {
+            anyhow::bail!("Storage quota can't be negative");
+        }
+        let updated = diesel::update(users::table)
+            .filter(users::uuid.eq(UUID::from(&user_uuid)))
+            .set(users::storage_quota_mb.eq(quota_mb))
+            .execute(&mut get_conn(context))
+            .context("Could not update user")?;
+        if updated == 0 {
+            anyhow::bail!("Could not find user");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_limits_requests_per_window() {
+        let limiter = RateLimiter::default();
+        let start = Instant::now();
+        assert!(limiter.check("ip:a", 2, start).is_ok());
+        assert!(limiter
+            .check("ip:a", 2, start + Duration::from_secs(10))
+            .is_ok());
+        assert_eq!(
+            limiter.check("ip:a", 2, start + Duration::from_secs(20)),
+            Err(LimitError::RateLimited {
+                retry_after: Duration::from_secs(40)
+            })
+        );
+        // Other clients have their own window
+        assert!(limiter
+            .check("ip:b", 2, start + Duration::from_secs(20))
+            .is_ok());
+    }
+
+    #[test]
+    fn it_frees_up_requests_as_the_window_slides() {
+        let limiter = RateLimiter::default();
+        let start = Instant::now();
+        assert!(limiter.check("user:a", 1, start).is_ok());
+        assert!(limiter
+            .check("user:a", 1, start + Duration::from_secs(59))
+            .is_err());
+        assert!(limiter
+            .check("user:a", 1, start + Duration::from_secs(60))
+            .is_ok());
+    }
+}
@@ -1,5 +1,47 @@
+mod album;
+mod audit;
+mod duplicate;
+mod events;
+mod history;
+mod limits;
+mod moderation;
+mod playlist;
+mod query_limits;
+mod quiet;
+mod schedule;
 mod screensaver;
+mod session;
+mod share;
+mod shuffle;
+mod tag;
+mod token;
+mod trash;
+mod transition;
 mod upload;
+mod user;
+mod validation;
+mod weighted;
 
+pub use album::*;
+pub use audit::*;
+pub use duplicate::*;
+pub use events::*;
+pub use history::*;
+pub use limits::*;
+pub use moderation::*;
+pub use playlist::*;
+pub use query_limits::*;
+pub use quiet::*;
+pub use schedule::*;
 pub use screensaver::*;
+pub use session::*;
+pub use share::*;
+pub use shuffle::*;
+pub use tag::*;
+pub use token::*;
+pub use trash::*;
+pub use transition::*;
 pub use upload::*;
+pub use user::*;
+pub use validation::*;
+pub use weighted::*;
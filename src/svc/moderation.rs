@@ -0,0 +1,113 @@
+use crate::{
+    config::ConfigSvc,
+    context::GraphQLContext,
+    db::get_conn,
+    models::{ModerationStatus, Upload, User},
+    schema::{uploads, users},
+    svc::UploadSvc,
+    uuid::UUID,
+};
+use anyhow::{Context, Result};
+use diesel::prelude::*;
+use tracing::info;
+use uuid::Uuid;
+
+pub struct ModerationSvc {}
+
+impl ModerationSvc {
+    /// Whether an upload by the context's user has to wait for a moderator
+    pub async fn requires_review(context: &GraphQLContext) -> Result<bool> {
+        let enabled = ConfigSvc::get_moderation_enabled(context)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to load moderation setting: {}", e))?;
+        Ok(enabled && !context.user.as_ref().is_some_and(is_trusted))
+    }
+
+    /// Uploads waiting for a moderator, oldest first
+    pub fn list_pending(context: &GraphQLContext, limit: i32, offset: i32) -> Result<Vec<Upload>> {
+        let limit: i64 = limit.into();
+        let offset: i64 = offset.into();
+
+        uploads::table
+            .filter(uploads::status.eq(ModerationStatus::Pending.as_str()))
+            .filter(uploads::deleted_at.is_null())
+            .select(Upload::as_select())
+            .order_by(uploads::uploaded_at.asc())
+            .limit(limit)
+            .offset(offset)
+            .load::<Upload>(&mut get_conn(context))
+            .context("Could not load pending uploads")
+    }
+
+    /// Let a pending or rejected upload through, it is shown right away like a new upload
+    pub async fn approve(context: &GraphQLContext, upload_uuid: Uuid) -> Result<Upload> {
+        let upload = UploadSvc::get(context, upload_uuid)?;
+        if upload.is_approved() {
+            anyhow::bail!("Upload is already approved");
+        }
+
+        Self::set_status(context, &upload, ModerationStatus::Approved, None)?;
+        let approved = UploadSvc::get(context, upload_uuid)?;
+        info!(
+            "Approved upload {:?}",
+            approved.name.as_deref().unwrap_or("Untitled")
+        );
+        UploadSvc::show_new(context, &approved).await?;
+        Ok(approved)
+    }
+
+    /// Turn down a pending upload, it stays visible to its owner along with the reason
+    pub fn reject(
+        context: &GraphQLContext,
+        upload_uuid: Uuid,
+        reason: Option<String>,
+    ) -> Result<Upload> {
+        let upload = UploadSvc::get(context, upload_uuid)?;
+        if upload.status != ModerationStatus::Pending.as_str() {
+            anyhow::bail!("Only pending uploads can be rejected");
+        }
+
+        let reason = reason
+            .map(|reason| reason.trim().to_owned())
+            .filter(|reason| !reason.is_empty());
+        Self::set_status(context, &upload, ModerationStatus::Rejected, reason)?;
+        UploadSvc::get(context, upload_uuid)
+    }
+
+    /// Let a user's uploads skip the queue, or make them wait again
+    pub fn set_trusted(context: &GraphQLContext, user_uuid: Uuid, trusted: bool) -> Result<()> {
+        let updated = diesel::update(users::table)
+            .filter(users::uuid.eq(UUID::from(&user_uuid)))
+            .set(users::trusted.eq(trusted))
+            .execute(&mut get_conn(context))
+            .context("Could not update user")?;
+        if updated == 0 {
+            anyhow::bail!("Could not find user");
+        }
+        Ok(())
+    }
+
+    fn set_status(
+        context: &GraphQLContext,
+        upload: &Upload,
+        status: ModerationStatus,
+        rejection_reason: Option<String>,
+    ) -> Result<()> {
+        diesel::update(uploads::table)
+            .filter(uploads::uuid.eq(upload.uuid))
+            .set((
+                uploads::status.eq(status.as_str()),
+                uploads::moderated_by.eq(context.user.as_ref().map(|user| user.uuid)),
+                uploads::moderated_at.eq(chrono::Utc::now().naive_utc()),
+                uploads::rejection_reason.eq(rejection_reason),
+            ))
+            .execute(&mut get_conn(context))
+            .context("Could not update upload")?;
+        Ok(())
+    }
+}
+
+/// Admins and trusted users don't need their uploads reviewed
+fn is_trusted(user: &User) -> bool {
+    user.is_admin || user.trusted
+}
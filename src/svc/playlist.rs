@@ -0,0 +1,156 @@
+use crate::{
+    context::GraphQLContext,
+    db::get_conn,
+    models::{DisplayFormat, ModerationStatus, Playlist, PlaylistItem, PlaylistItemInput, Upload},
+    schema::{playlist_items, playlists, schedule_rules, uploads},
+    svc::{ScheduleSvc, UploadSvc},
+    uuid::UUID,
+};
+use anyhow::{Context, Result};
+use diesel::prelude::*;
+use uuid::Uuid;
+
+pub struct PlaylistSvc {}
+
+impl PlaylistSvc {
+    pub fn get(context: &GraphQLContext, playlist_uuid: Uuid) -> Result<Playlist> {
+        playlists::table
+            .filter(playlists::uuid.eq(UUID::from(&playlist_uuid)))
+            .first(&mut get_conn(context))
+            .context("Could not find playlist")
+    }
+    pub fn list(context: &GraphQLContext) -> Result<Vec<Playlist>> {
+        playlists::table
+            .order_by(playlists::name.asc())
+            .load::<Playlist>(&mut get_conn(context))
+            .context("Could not load playlists")
+    }
+    pub fn create(
+        context: &GraphQLContext,
+        name: &str,
+        items: &[PlaylistItemInput],
+    ) -> Result<Playlist> {
+        let playlist = Playlist {
+            uuid: UUID::random(),
+            name: name.trim().to_owned(),
+            created_at: Some(chrono::Utc::now().naive_utc()),
+        };
+        if playlist.name.is_empty() {
+            anyhow::bail!("Playlist name cannot be empty");
+        }
+
+        diesel::insert_into(playlists::table)
+            .values(&playlist)
+            .execute(&mut get_conn(context))
+            .context("Could not create playlist")?;
+
+        Self::set_items(context, playlist.uuid.into(), items)
+    }
+    pub fn rename(context: &GraphQLContext, playlist_uuid: Uuid, name: &str) -> Result<Playlist> {
+        let name = name.trim();
+        if name.is_empty() {
+            anyhow::bail!("Playlist name cannot be empty");
+        }
+
+        diesel::update(playlists::table)
+            .filter(playlists::uuid.eq(UUID::from(&playlist_uuid)))
+            .set(playlists::name.eq(name))
+            .execute(&mut get_conn(context))
+            .context("Could not rename playlist")?;
+
+        Self::get(context, playlist_uuid)
+    }
+    /// Replace the contents of a playlist, items are played in the order given
+    pub fn set_items(
+        context: &GraphQLContext,
+        playlist_uuid: Uuid,
+        items: &[PlaylistItemInput],
+    ) -> Result<Playlist> {
+        let playlist = Self::get(context, playlist_uuid)?;
+
+        let mut entries = Vec::with_capacity(items.len());
+        for (position, item) in items.iter().enumerate() {
+            if item.duration_seconds.is_some_and(|seconds| seconds <= 0) {
+                anyhow::bail!("Item durations must be positive");
+            }
+            UploadSvc::get_visible(context, item.upload_uuid)?;
+
+            entries.push(PlaylistItem {
+                playlist_uuid: playlist.uuid,
+                position: position as i32,
+                upload_uuid: UUID::from(&item.upload_uuid),
+                duration_seconds: item.duration_seconds,
+            });
+        }
+
+        let mut conn = get_conn(context);
+        conn.transaction(|conn| {
+            diesel::delete(playlist_items::table)
+                .filter(playlist_items::playlist_uuid.eq(playlist.uuid))
+                .execute(conn)?;
+            if !entries.is_empty() {
+                diesel::insert_into(playlist_items::table)
+                    .values(&entries)
+                    .execute(conn)?;
+            }
+            Ok::<_, diesel::result::Error>(())
+        })
+        .context("Could not update playlist items")?;
+
+        Ok(playlist)
+    }
+    pub async fn delete(context: &GraphQLContext, playlist_uuid: Uuid) -> Result<()> {
+        let mut conn = get_conn(context);
+        conn.transaction(|conn| {
+            diesel::delete(playlist_items::table)
+                .filter(playlist_items::playlist_uuid.eq(UUID::from(&playlist_uuid)))
+                .execute(conn)?;
+            // Rules playing the playlist would point at nothing
+            diesel::delete(schedule_rules::table)
+                .filter(schedule_rules::playlist_uuid.eq(UUID::from(&playlist_uuid)))
+                .execute(conn)?;
+            diesel::delete(playlists::table)
+                .filter(playlists::uuid.eq(UUID::from(&playlist_uuid)))
+                .execute(conn)
+        })
+        .context("Could not delete playlist")?;
+        ScheduleSvc::notify_screensaver(context).await;
+
+        // Fall back to the regular slideshow if this playlist was running
+        for screensaver in context.screensavers() {
+            if screensaver.get_state().await.playlist == Some(playlist_uuid) {
+                screensaver.set_playlist(None).await?;
+            }
+        }
+
+        Ok(())
+    }
+    /// Items of a playlist in play order, skipping uploads that are in the trash
+    pub fn list_items(context: &GraphQLContext, playlist_uuid: Uuid) -> Result<Vec<PlaylistItem>> {
+        playlist_items::table
+            .inner_join(uploads::table)
+            .filter(playlist_items::playlist_uuid.eq(UUID::from(&playlist_uuid)))
+            .filter(uploads::deleted_at.is_null())
+            .select(PlaylistItem::as_select())
+            .order_by(playlist_items::position.asc())
+            .load::<PlaylistItem>(&mut get_conn(context))
+            .context("Could not load playlist items")
+    }
+    /// Playlist items for one display along with their uploads, in play order
+    pub fn list_slides(
+        context: &GraphQLContext,
+        playlist_uuid: Uuid,
+        display: DisplayFormat,
+    ) -> Result<Vec<(PlaylistItem, Upload)>> {
+        playlist_items::table
+            .inner_join(uploads::table)
+            .filter(playlist_items::playlist_uuid.eq(UUID::from(&playlist_uuid)))
+            .filter(uploads::deleted_at.is_null())
+            .filter(uploads::status.eq(ModerationStatus::Approved.as_str()))
+            .filter(uploads::display.eq(display.to_string()))
+            .select((PlaylistItem::as_select(), Upload::as_select()))
+            .order_by(playlist_items::position.asc())
+            .load::<(PlaylistItem, Upload)>(&mut get_conn(context))
+            .context("Could not load playlist slides")
+    }
+}
@@ -0,0 +1,298 @@
+use crate::get_env_typed;
+use juniper::{
+    parser::parse_document_source, Definition, OperationType, ScalarValue, SchemaType, Selection,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+/// How deep and how big a GraphQL query may get before it is turned away unexecuted. Juniper
+/// has no limits of its own, so queries are measured here before they reach the schema.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueryLimits {
+    /// Deepest nesting of fields, `{ a { b } }` has a depth of 2
+    pub max_depth: usize,
+    /// Most fields in one operation, fragments count wherever they are spread
+    pub max_complexity: usize,
+}
+
+impl QueryLimits {
+    /// Limits from `GRAPHQL_MAX_DEPTH` and `GRAPHQL_MAX_COMPLEXITY`, generous enough for the
+    /// introspection query of GraphiQL
+    pub fn from_env() -> Self {
+        Self {
+            max_depth: get_env_typed("GRAPHQL_MAX_DEPTH", 20),
+            max_complexity: get_env_typed("GRAPHQL_MAX_COMPLEXITY", 500),
+        }
+    }
+
+    /// Check every operation of a query document against the limits. The query is parsed the
+    /// way Juniper parses it for `schema`, arguments, variables and directives don't count.
+    pub fn check<S: ScalarValue>(
+        &self,
+        query: &str,
+        schema: &SchemaType<S>,
+    ) -> Result<(), QueryLimitError> {
+        let document = parse_document_source(query, schema)
+            .map_err(|e| QueryLimitError::Invalid(e.item.to_string()))?;
+        let mut operations = Vec::new();
+        let mut fragments = HashMap::new();
+        for definition in &document {
+            match definition {
+                Definition::Operation(operation) => {
+                    operations.push(&operation.item.selection_set[..])
+                }
+                Definition::Fragment(fragment) => {
+                    fragments.insert(fragment.item.name.item, &fragment.item.selection_set[..]);
+                }
+            }
+        }
+
+        let mut measure = Measure::new(&fragments);
+        for operation in operations {
+            let (depth, complexity) = measure.selections(operation)?;
+            if depth > self.max_depth {
+                return Err(QueryLimitError::TooDeep {
+                    depth,
+                    max_depth: self.max_depth,
+                });
+            }
+            if complexity > self.max_complexity {
+                return Err(QueryLimitError::TooComplex {
+                    complexity,
+                    max_complexity: self.max_complexity,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether a query document contains a mutation. Documents that don't parse count as read only,
+/// Juniper rejects them before anything runs.
+pub fn has_mutation<S: ScalarValue>(query: &str, schema: &SchemaType<S>) -> bool {
+    let Ok(document) = parse_document_source(query, schema) else {
+        return false;
+    };
+    document.iter().any(|definition| {
+        matches!(
+            definition,
+            Definition::Operation(operation)
+                if matches!(operation.item.operation_type, OperationType::Mutation)
+        )
+    })
+}
+
+/// Why a query was turned away before it ran
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryLimitError {
+    TooDeep {
+        depth: usize,
+        max_depth: usize,
+    },
+    TooComplex {
+        complexity: usize,
+        max_complexity: usize,
+    },
+    /// The query couldn't be measured, Juniper would reject it as well
+    Invalid(String),
+}
+
+impl QueryLimitError {
+    /// Error code for the `extensions` of the GraphQL error
+    pub fn code(&self) -> &'static str {
+        match self {
+            QueryLimitError::TooDeep { .. } => "QUERY_TOO_DEEP",
+            QueryLimitError::TooComplex { .. } => "QUERY_TOO_COMPLEX",
+            QueryLimitError::Invalid(_) => "GRAPHQL_PARSE_FAILED",
+        }
+    }
+}
+
+impl fmt::Display for QueryLimitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryLimitError::TooDeep { depth, max_depth } => write!(
+                f,
+                "Query is nested {depth} levels deep, at most {max_depth} are allowed"
+            ),
+            QueryLimitError::TooComplex {
+                complexity,
+                max_complexity,
+            } => write!(
+                f,
+                "Query selects {complexity} fields, at most {max_complexity} are allowed"
+            ),
+            QueryLimitError::Invalid(message) => write!(f, "Invalid query: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for QueryLimitError {}
+
+/// Depth and number of fields of selections. Fragments are measured once however often they are
+/// spread, so queries spreading fragments into fragments can't make this slow.
+struct Measure<'a, S> {
+    fragments: &'a HashMap<&'a str, &'a [Selection<'a, S>]>,
+    measured: HashMap<&'a str, (usize, usize)>,
+    /// Fragments being measured, spreading one of them again would never end
+    spreading: HashSet<&'a str>,
+}
+
+impl<'a, S> Measure<'a, S> {
+    fn new(fragments: &'a HashMap<&'a str, &'a [Selection<'a, S>]>) -> Self {
+        Self {
+            fragments,
+            measured: HashMap::new(),
+            spreading: HashSet::new(),
+        }
+    }
+
+    fn selections(
+        &mut self,
+        selections: &'a [Selection<'a, S>],
+    ) -> Result<(usize, usize), QueryLimitError> {
+        let mut depth = 0;
+        let mut complexity = 0_usize;
+        for selection in selections {
+            let (selection_depth, selection_complexity) = match selection {
+                Selection::Field(field) => {
+                    let children = field.item.selection_set.as_deref().unwrap_or_default();
+                    let (children_depth, children_complexity) = self.selections(children)?;
+                    (children_depth + 1, children_complexity.saturating_add(1))
+                }
+                Selection::InlineFragment(fragment) => {
+                    self.selections(&fragment.item.selection_set)?
+                }
+                Selection::FragmentSpread(spread) => self.fragment(spread.item.name.item)?,
+            };
+            depth = depth.max(selection_depth);
+            complexity = complexity.saturating_add(selection_complexity);
+        }
+        Ok((depth, complexity))
+    }
+
+    fn fragment(&mut self, name: &'a str) -> Result<(usize, usize), QueryLimitError> {
+        if let Some(measured) = self.measured.get(name) {
+            return Ok(*measured);
+        }
+        let fragment = *self
+            .fragments
+            .get(name)
+            .ok_or_else(|| QueryLimitError::Invalid(format!("Unknown fragment {name}")))?;
+        if !self.spreading.insert(name) {
+            return Err(QueryLimitError::Invalid(format!(
+                "Fragment {name} spreads itself"
+            )));
+        }
+        let measured = self.selections(fragment)?;
+        self.spreading.remove(name);
+        self.measured.insert(name, measured);
+        Ok(measured)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphql::create_schema;
+
+    const LIMITS: QueryLimits = QueryLimits {
+        max_depth: 3,
+        max_complexity: 6,
+    };
+
+    #[test]
+    fn it_measures_depth_through_fragments() {
+        let schema = create_schema();
+        assert!(LIMITS
+            .check("{ listAlbums { uploads { name } } }", &schema.schema)
+            .is_ok());
+        assert_eq!(
+            LIMITS.check("{ a { b { c { d } } } }", &schema.schema),
+            Err(QueryLimitError::TooDeep {
+                depth: 4,
+                max_depth: 3
+            })
+        );
+        assert_eq!(
+            LIMITS.check(
+                "query Deep { a { ...F } } fragment F on A { b { ... on B { c { d } } } }",
+                &schema.schema
+            ),
+            Err(QueryLimitError::TooDeep {
+                depth: 4,
+                max_depth: 3
+            })
+        );
+    }
+
+    #[test]
+    fn it_counts_fields_of_every_spread() {
+        let schema = create_schema();
+        let query = "{ a { ...F } b { ...F } } fragment F on T { x y }";
+        assert!(LIMITS.check(query, &schema.schema).is_ok());
+        assert_eq!(
+            LIMITS.check(
+                "{ a { ...F } b { ...F } c } fragment F on T { x y }",
+                &schema.schema
+            ),
+            Err(QueryLimitError::TooComplex {
+                complexity: 7,
+                max_complexity: 6
+            })
+        );
+    }
+
+    #[test]
+    fn it_skips_arguments_and_strings() {
+        let schema = create_schema();
+        let query = r#"
+            # Input objects use braces too
+            query Log($limit: Int = 10) @cached {
+                log: auditLog(filter: { action: "{ a { b { c } } }" }, limit: $limit) {
+                    actor @include(if: true) { username }
+                    oldValue
+                }
+                upload: getUpload(uploadUuid: "{ \"}\" }") { weight }
+            }
+        "#;
+        assert!(LIMITS.check(query, &schema.schema).is_ok());
+    }
+
+    #[test]
+    fn it_rejects_what_it_cant_measure() {
+        let schema = create_schema();
+        assert!(matches!(
+            LIMITS.check("{ a { ...Missing } }", &schema.schema),
+            Err(QueryLimitError::Invalid(_))
+        ));
+        assert!(matches!(
+            LIMITS.check(
+                "{ a { ...F } } fragment F on T { a { ...F } }",
+                &schema.schema
+            ),
+            Err(QueryLimitError::Invalid(_))
+        ));
+        assert!(matches!(
+            LIMITS.check("{ a { b }", &schema.schema),
+            Err(QueryLimitError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn it_finds_mutations() {
+        let schema = create_schema();
+        assert!(has_mutation(
+            "mutation { pauseScreensaver }",
+            &schema.schema
+        ));
+        assert!(has_mutation(
+            "query Q { listAlbums { name } } mutation M { pauseScreensaver }",
+            &schema.schema
+        ));
+        assert!(!has_mutation("{ listAlbums { name } }", &schema.schema));
+        assert!(!has_mutation("query { pauseScreensaver }", &schema.schema));
+    }
+}
@@ -0,0 +1,161 @@
+use crate::{
+    config::ConfigSvc,
+    context::GraphQLContext,
+    models::{QuietFrame, QuietHours, QuietHoursInput, Upload, Weekday},
+    svc::{
+        upload::{decode_rgb, RGB_HEIGHT, RGB_WIDTH},
+        ScheduleSvc,
+    },
+};
+use anyhow::Result;
+use chrono::{DateTime, Datelike, Duration, NaiveDateTime, Utc};
+
+/// Brightness of a dimmed frame, in percent of the original image
+const DIM_BRIGHTNESS_PERCENT: u16 = 15;
+
+pub struct QuietHoursSvc {}
+
+impl QuietHoursSvc {
+    /// Validate and store the quiet hours, replacing all existing ones
+    pub async fn set(
+        context: &GraphQLContext,
+        input: Vec<QuietHoursInput>,
+    ) -> Result<Vec<QuietHours>> {
+        let mut quiet_hours: Vec<QuietHours> = Vec::with_capacity(input.len());
+        for hours in input {
+            if quiet_hours
+                .iter()
+                .any(|other| other.weekday == hours.weekday)
+            {
+                anyhow::bail!(
+                    "Quiet hours for {} given more than once",
+                    hours.weekday.as_str()
+                );
+            }
+            let start_time = ScheduleSvc::parse_time(&hours.start_time)?;
+            let end_time = ScheduleSvc::parse_time(&hours.end_time)?;
+            quiet_hours.push(QuietHours {
+                weekday: hours.weekday,
+                start_time: start_time.format("%H:%M").to_string(),
+                end_time: end_time.format("%H:%M").to_string(),
+            });
+        }
+
+        ConfigSvc::set_quiet_hours(context, &quiet_hours)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to set quiet hours: {}", e))?;
+
+        Self::notify_screensaver(context).await;
+        Ok(quiet_hours)
+    }
+
+    pub async fn set_frame(context: &GraphQLContext, frame: QuietFrame) -> Result<()> {
+        ConfigSvc::set_quiet_frame(context, frame)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to set quiet frame: {}", e))?;
+
+        Self::notify_screensaver(context).await;
+        Ok(())
+    }
+
+    /// Whether `now` falls into quiet hours, evaluated in the configured timezone
+    pub async fn is_quiet(context: &GraphQLContext, now: DateTime<Utc>) -> Result<bool> {
+        let timezone = ConfigSvc::get_timezone(context)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to load timezone: {}", e))?;
+        let quiet_hours = ConfigSvc::get_quiet_hours(context)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to load quiet hours: {}", e))?;
+
+        let local = now.with_timezone(&timezone).naive_local();
+        Ok(Self::is_quiet_at(&quiet_hours, local))
+    }
+
+    /// Whether the local time falls into any of the quiet hours. A window that ends before it
+    /// starts belongs to the weekday it starts on and runs into the next morning.
+    pub fn is_quiet_at(quiet_hours: &[QuietHours], local: NaiveDateTime) -> bool {
+        let time = local.time();
+        let today = Weekday::from(local.weekday());
+        let yesterday = Weekday::from((local - Duration::days(1)).weekday());
+
+        quiet_hours.iter().any(|hours| {
+            let (Ok(start), Ok(end)) = (
+                ScheduleSvc::parse_time(&hours.start_time),
+                ScheduleSvc::parse_time(&hours.end_time),
+            ) else {
+                return false;
+            };
+
+            if start < end {
+                return hours.weekday == today && time >= start && time < end;
+            }
+            (hours.weekday == today && time >= start) || (hours.weekday == yesterday && time < end)
+        })
+    }
+
+    /// Build the raw rgb8 frame shown during quiet hours
+    pub fn frame(frame: QuietFrame, current: Option<&Upload>) -> Result<Vec<u8>> {
+        match (frame, current) {
+            (QuietFrame::Dim, Some(upload)) => {
+                let mut pixels = decode_rgb(upload)?.into_raw();
+                for channel in pixels.iter_mut() {
+                    *channel = (*channel as u16 * DIM_BRIGHTNESS_PERCENT / 100) as u8;
+                }
+                Ok(pixels)
+            }
+            // Nothing to dim, fall back to a blank frame
+            _ => Ok(vec![0; RGB_WIDTH * RGB_HEIGHT * 3]),
+        }
+    }
+
+    /// Let the screensaver pick up changes right away instead of on its next check
+    async fn notify_screensaver(context: &GraphQLContext) {
+        for screensaver in context.screensavers() {
+            if let Err(e) = screensaver.on_quiet_hours_changed().await {
+                tracing::warn!("Failed to notify screensaver of quiet hours change: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn hours(weekday: Weekday, start: &str, end: &str) -> QuietHours {
+        QuietHours {
+            weekday,
+            start_time: start.to_owned(),
+            end_time: end.to_owned(),
+        }
+    }
+
+    fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        // 2026-01-12 is a Monday
+        NaiveDate::from_ymd_opt(2026, 1, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn it_is_quiet_within_a_day() {
+        let quiet_hours = [hours(Weekday::Saturday, "13:00", "15:00")];
+
+        assert!(QuietHoursSvc::is_quiet_at(&quiet_hours, at(17, 13, 0)));
+        assert!(!QuietHoursSvc::is_quiet_at(&quiet_hours, at(17, 15, 0)));
+        assert!(!QuietHoursSvc::is_quiet_at(&quiet_hours, at(12, 14, 0)));
+    }
+
+    #[test]
+    fn it_runs_overnight_from_the_starting_weekday() {
+        let quiet_hours = [hours(Weekday::Monday, "22:00", "07:00")];
+
+        assert!(QuietHoursSvc::is_quiet_at(&quiet_hours, at(12, 23, 30)));
+        assert!(QuietHoursSvc::is_quiet_at(&quiet_hours, at(13, 6, 59)));
+        assert!(!QuietHoursSvc::is_quiet_at(&quiet_hours, at(13, 7, 0)));
+        assert!(!QuietHoursSvc::is_quiet_at(&quiet_hours, at(13, 23, 30)));
+        assert!(!QuietHoursSvc::is_quiet_at(&quiet_hours, at(12, 6, 0)));
+    }
+}
@@ -0,0 +1,303 @@
+use crate::{
+    config::ConfigSvc,
+    context::GraphQLContext,
+    db::get_conn,
+    models::{ScheduleRule, ScheduleRuleInput, Weekday},
+    schema::schedule_rules,
+    svc::{PlaylistSvc, UploadSvc},
+    uuid::UUID,
+};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Datelike, Duration, NaiveDateTime, NaiveTime, Utc};
+use diesel::prelude::*;
+use uuid::Uuid;
+
+pub const ALL_WEEKDAYS: [Weekday; 7] = [
+    Weekday::Monday,
+    Weekday::Tuesday,
+    Weekday::Wednesday,
+    Weekday::Thursday,
+    Weekday::Friday,
+    Weekday::Saturday,
+    Weekday::Sunday,
+];
+
+pub struct ScheduleSvc {}
+
+impl ScheduleSvc {
+    pub fn get(context: &GraphQLContext, rule_uuid: Uuid) -> Result<ScheduleRule> {
+        schedule_rules::table
+            .filter(schedule_rules::uuid.eq(UUID::from(&rule_uuid)))
+            .first(&mut get_conn(context))
+            .context("Could not find schedule rule")
+    }
+    pub fn list(context: &GraphQLContext) -> Result<Vec<ScheduleRule>> {
+        schedule_rules::table
+            .order_by((
+                schedule_rules::priority.desc(),
+                schedule_rules::created_at.asc(),
+            ))
+            .load::<ScheduleRule>(&mut get_conn(context))
+            .context("Could not load schedule rules")
+    }
+    pub async fn create(context: &GraphQLContext, input: ScheduleRuleInput) -> Result<ScheduleRule> {
+        let rule = Self::build_rule(
+            context,
+            UUID::random(),
+            input,
+            Some(Utc::now().naive_utc()),
+        )?;
+
+        diesel::insert_into(schedule_rules::table)
+            .values(&rule)
+            .execute(&mut get_conn(context))
+            .context("Could not create schedule rule")?;
+
+        Self::notify_screensaver(context).await;
+        Self::get(context, rule.uuid.into())
+    }
+    pub async fn update(
+        context: &GraphQLContext,
+        rule_uuid: Uuid,
+        input: ScheduleRuleInput,
+    ) -> Result<ScheduleRule> {
+        let existing = Self::get(context, rule_uuid)?;
+        let rule = Self::build_rule(context, existing.uuid, input, existing.created_at)?;
+
+        diesel::update(schedule_rules::table)
+            .filter(schedule_rules::uuid.eq(&rule.uuid))
+            .set(&rule)
+            .execute(&mut get_conn(context))
+            .context("Could not update schedule rule")?;
+
+        Self::notify_screensaver(context).await;
+        Self::get(context, rule_uuid)
+    }
+    pub async fn delete(context: &GraphQLContext, rule_uuid: Uuid) -> Result<()> {
+        diesel::delete(schedule_rules::table)
+            .filter(schedule_rules::uuid.eq(UUID::from(&rule_uuid)))
+            .execute(&mut get_conn(context))
+            .context("Could not delete schedule rule")?;
+
+        Self::notify_screensaver(context).await;
+        Ok(())
+    }
+
+    /// The highest priority enabled rule active at `now`, evaluated in the configured timezone
+    pub async fn active_rule(
+        context: &GraphQLContext,
+        now: DateTime<Utc>,
+    ) -> Result<Option<ScheduleRule>> {
+        let timezone = ConfigSvc::get_timezone(context)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to load timezone: {}", e))?;
+        let local = now.with_timezone(&timezone).naive_local();
+
+        let rules = schedule_rules::table
+            .filter(schedule_rules::enabled.eq(true))
+            .order_by((
+                schedule_rules::priority.desc(),
+                schedule_rules::created_at.asc(),
+            ))
+            .load::<ScheduleRule>(&mut get_conn(context))
+            .context("Could not load schedule rules")?;
+
+        Ok(rules.into_iter().find(|rule| rule.is_active_at(local)))
+    }
+
+    pub fn weekdays_to_mask(weekdays: &[Weekday]) -> i32 {
+        weekdays
+            .iter()
+            .fold(0, |mask, weekday| mask | Self::weekday_bit(*weekday))
+    }
+
+    pub fn mask_to_weekdays(mask: i32) -> Vec<Weekday> {
+        ALL_WEEKDAYS
+            .into_iter()
+            .filter(|weekday| mask & Self::weekday_bit(*weekday) != 0)
+            .collect()
+    }
+
+    fn weekday_bit(weekday: Weekday) -> i32 {
+        let index = ALL_WEEKDAYS
+            .iter()
+            .position(|w| *w == weekday)
+            .unwrap_or_default();
+        1 << index
+    }
+
+    /// Parse a local HH:MM time as used by schedule rules and quiet hours
+    pub fn parse_time(value: &str) -> Result<NaiveTime> {
+        NaiveTime::parse_from_str(value.trim(), "%H:%M")
+            .with_context(|| format!("Invalid time {value:?}, expected HH:MM"))
+    }
+
+    /// Validate an input and turn it into a rule
+    fn build_rule(
+        context: &GraphQLContext,
+        uuid: UUID,
+        input: ScheduleRuleInput,
+        created_at: Option<NaiveDateTime>,
+    ) -> Result<ScheduleRule> {
+        let name = input.name.trim().to_owned();
+        if name.is_empty() {
+            anyhow::bail!("Schedule rule name cannot be empty");
+        }
+        let start_time = Self::parse_time(&input.start_time)?;
+        let end_time = Self::parse_time(&input.end_time)?;
+        if input.day_of_month.is_some_and(|day| !(1..=31).contains(&day)) {
+            anyhow::bail!("Day of month must be between 1 and 31");
+        }
+
+        match (input.upload_uuid, input.playlist_uuid) {
+            (Some(upload_uuid), None) => {
+                UploadSvc::get_visible(context, upload_uuid)?;
+            }
+            (None, Some(playlist_uuid)) => {
+                PlaylistSvc::get(context, playlist_uuid)?;
+            }
+            _ => anyhow::bail!("A schedule rule needs either an upload or a playlist"),
+        }
+
+        let weekdays = match input.weekdays {
+            Some(weekdays) => Self::weekdays_to_mask(&weekdays),
+            None => Self::weekdays_to_mask(&ALL_WEEKDAYS),
+        };
+        if weekdays == 0 {
+            anyhow::bail!("A schedule rule needs at least one weekday");
+        }
+
+        Ok(ScheduleRule {
+            uuid,
+            name,
+            weekdays,
+            day_of_month: input.day_of_month,
+            start_time: start_time.format("%H:%M").to_string(),
+            end_time: end_time.format("%H:%M").to_string(),
+            upload_uuid: input.upload_uuid.as_ref().map(UUID::from),
+            playlist_uuid: input.playlist_uuid.as_ref().map(UUID::from),
+            priority: input.priority.unwrap_or(0),
+            enabled: input.enabled.unwrap_or(true),
+            created_at,
+        })
+    }
+
+    /// Let the screensaver pick up rule changes right away instead of on its next check
+    pub(crate) async fn notify_screensaver(context: &GraphQLContext) {
+        for screensaver in context.screensavers() {
+            if let Err(e) = screensaver.on_schedule_changed().await {
+                tracing::warn!("Failed to notify screensaver of schedule change: {}", e);
+            }
+        }
+    }
+}
+
+impl ScheduleRule {
+    /// Whether the rule applies at the given local time
+    pub fn is_active_at(&self, local: NaiveDateTime) -> bool {
+        let (Ok(start), Ok(end)) = (
+            ScheduleSvc::parse_time(&self.start_time),
+            ScheduleSvc::parse_time(&self.end_time),
+        ) else {
+            return false;
+        };
+        let time = local.time();
+
+        if start < end {
+            return self.applies_on(local) && time >= start && time < end;
+        }
+
+        // The window wraps past midnight (or covers the whole day when start == end), so the
+        // early part belongs to the window that started the day before
+        (time >= start && self.applies_on(local))
+            || (time < end && self.applies_on(local - Duration::days(1)))
+    }
+
+    /// Whether the rule's weekday and day of month restrictions allow the given day
+    fn applies_on(&self, day: NaiveDateTime) -> bool {
+        let weekday_bit = 1 << day.weekday().num_days_from_monday();
+        if self.weekdays & weekday_bit == 0 {
+            return false;
+        }
+        self.day_of_month
+            .is_none_or(|day_of_month| day.day() == day_of_month as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn rule(weekdays: &[Weekday], day_of_month: Option<i32>, start: &str, end: &str) -> ScheduleRule {
+        ScheduleRule {
+            uuid: UUID::random(),
+            name: "test".to_owned(),
+            weekdays: ScheduleSvc::weekdays_to_mask(weekdays),
+            day_of_month,
+            start_time: start.to_owned(),
+            end_time: end.to_owned(),
+            upload_uuid: Some(UUID::random()),
+            playlist_uuid: None,
+            priority: 0,
+            enabled: true,
+            created_at: None,
+        }
+    }
+
+    fn at(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(year, month, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn it_matches_weekday_windows() {
+        let weekdays = [
+            Weekday::Monday,
+            Weekday::Tuesday,
+            Weekday::Wednesday,
+            Weekday::Thursday,
+            Weekday::Friday,
+        ];
+        let lunch = rule(&weekdays, None, "11:00", "13:00");
+
+        // 2026-01-12 is a Monday
+        assert!(lunch.is_active_at(at(2026, 1, 12, 11, 0)));
+        assert!(lunch.is_active_at(at(2026, 1, 12, 12, 59)));
+        assert!(!lunch.is_active_at(at(2026, 1, 12, 13, 0)));
+        assert!(!lunch.is_active_at(at(2026, 1, 12, 10, 59)));
+        assert!(!lunch.is_active_at(at(2026, 1, 17, 12, 0)));
+    }
+
+    #[test]
+    fn it_matches_day_of_month() {
+        let drill = rule(&ALL_WEEKDAYS, Some(3), "00:00", "00:00");
+
+        assert!(drill.is_active_at(at(2026, 2, 3, 0, 0)));
+        assert!(drill.is_active_at(at(2026, 2, 3, 23, 59)));
+        assert!(!drill.is_active_at(at(2026, 2, 4, 0, 0)));
+        assert!(!drill.is_active_at(at(2026, 2, 2, 23, 59)));
+    }
+
+    #[test]
+    fn it_wraps_windows_past_midnight() {
+        let late = rule(&[Weekday::Friday], None, "22:00", "02:00");
+
+        // 2026-01-16 is a Friday
+        assert!(late.is_active_at(at(2026, 1, 16, 23, 0)));
+        assert!(late.is_active_at(at(2026, 1, 17, 1, 30)));
+        assert!(!late.is_active_at(at(2026, 1, 17, 2, 0)));
+        assert!(!late.is_active_at(at(2026, 1, 16, 1, 30)));
+    }
+
+    #[test]
+    fn it_round_trips_weekday_masks() {
+        let weekdays = vec![Weekday::Monday, Weekday::Saturday];
+        let mask = ScheduleSvc::weekdays_to_mask(&weekdays);
+
+        assert_eq!(mask, 0b0100001);
+        assert_eq!(ScheduleSvc::mask_to_weekdays(mask), weekdays);
+    }
+}
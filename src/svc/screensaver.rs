@@ -2,77 +2,246 @@ use crate::{
     config::ConfigSvc,
     context::GraphQLContext,
     db::get_conn,
-    models::{DisplayFormat, Upload},
-    schema::uploads,
-    svc::upload::push_upload_to_device,
+    models::{
+        DisplayFormat, DisplayTrigger, ModerationStatus, ScheduleRule, ScreensaverChange,
+        SlideshowMode, Upload,
+    },
+    schema::{album_uploads, uploads},
+    svc::{
+        frame_count, is_full_frame, screen_time_shares, smoothed_frame_time, transition_frame,
+        upload::{decode_rgb, push_rgb_frame, push_upload_to_device},
+        Event, HistorySvc, PlaylistSvc, QuietHoursSvc, ScheduleSvc, ShuffleOrder, UploadSvc,
+        WeightedOrder,
+    },
+    uuid::UUID,
 };
 use anyhow::{Context, Result};
-use diesel::prelude::*;
+use chrono::NaiveDateTime;
+use diesel::{prelude::*, sqlite::Sqlite};
 use std::{
     sync::{
-        atomic::{AtomicBool, AtomicUsize, Ordering},
+        atomic::{AtomicBool, Ordering},
         Arc,
     },
     time::Duration,
 };
-use tokio::{sync::{mpsc, RwLock}, time::sleep};
+use tokio::{
+    sync::{mpsc, Mutex, RwLock},
+    time::{interval, sleep, sleep_until, Instant},
+};
 use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// How often schedule rules and quiet hours are evaluated
+const SCHEDULE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// An upload queued for display, with an optional dwell time overriding the global interval
+#[derive(Debug, Clone)]
+pub struct Slide {
+    pub upload: Upload,
+    pub duration_seconds: Option<u64>,
+}
+
+/// The upload on screen, identified independently of its position in the slide list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CurrentSlide {
+    pub upload: UUID,
+    /// Kept to find the neighbours of an upload that is no longer in the slideshow
+    pub uploaded_at: Option<NaiveDateTime>,
+}
+
+/// An upload shown on request that holds off the slideshow for a while
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PinnedUpload {
+    pub upload: UUID,
+    pub until: NaiveDateTime,
+}
+
+/// Where the current upload sits in a list of slides
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Position {
+    /// Nothing shown yet
+    Unknown,
+    /// The current upload is the slide at this index
+    At(usize),
+    /// The current upload left the slideshow, the slide at this index took its place
+    Gone(usize),
+}
+
+impl Position {
+    /// Index of the slide after the current one
+    fn next(self, len: usize) -> usize {
+        match self {
+            Position::Unknown => 0,
+            Position::At(idx) => (idx + 1) % len,
+            Position::Gone(idx) => idx % len,
+        }
+    }
+
+    /// Index of the slide before the current one
+    fn previous(self, len: usize) -> usize {
+        match self {
+            Position::Unknown => len - 1,
+            Position::At(idx) | Position::Gone(idx) => (idx + len - 1) % len,
+        }
+    }
+
+    /// Index of the current slide, or of the one that took its place
+    fn current(self, len: usize) -> usize {
+        match self {
+            Position::Unknown => 0,
+            Position::At(idx) => idx,
+            Position::Gone(idx) => idx % len,
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct ScreensaverState {
     pub is_running: bool,
+    /// Upload on screen, next and previous are worked out relative to it
+    pub current: Option<CurrentSlide>,
+    /// Position of the current upload in the slideshow. Only a tie breaker when an upload
+    /// appears more than once, e.g. in a playlist.
     pub current_index: usize,
     pub upload_count: usize,
     pub interval_seconds: u64,
+    pub album: Option<Uuid>,
+    pub playlist: Option<Uuid>,
+    /// Dwell time of the current slide when it overrides `interval_seconds`
+    pub slide_duration_seconds: Option<u64>,
+    /// Schedule rule currently overriding the slideshow
+    pub schedule: Option<ScheduleRule>,
+    /// Upload and position to return to once the active schedule rule ends
+    pub resume: Option<(CurrentSlide, usize)>,
+    pub mode: SlideshowMode,
+    /// Play order used in shuffle mode
+    pub shuffle: ShuffleOrder,
+    /// Pick history used in weighted mode
+    pub weighted: WeightedOrder,
+    /// Quiet hours are in effect, the display is blanked and the slideshow holds still
+    pub quiet: bool,
+    /// Upload shown by `showUpload` in place of the slideshow, `current` is where it resumes
+    pub pinned: Option<PinnedUpload>,
 }
 
 pub struct ScreensaverSvc {
+    /// Display format this slideshow drives, each display runs its own slideshow
+    display: DisplayFormat,
     state: Arc<RwLock<ScreensaverState>>,
     is_running: Arc<AtomicBool>,
-    current_index: Arc<AtomicUsize>,
     context: Arc<GraphQLContext>,
     reset_timer_tx: Arc<RwLock<Option<mpsc::UnboundedSender<()>>>>,
+    /// Held while pushing to the RGB device so transitions don't interleave
+    rgb_device: Arc<Mutex<RgbDevice>>,
+}
+
+/// What the RGB device shows and how fast it takes frames, transitions start from it
+#[derive(Debug, Default)]
+struct RgbDevice {
+    frame: Option<Vec<u8>>,
+    frame_time: Option<Duration>,
 }
 
 impl ScreensaverSvc {
-    pub fn new(context: Arc<GraphQLContext>) -> Self {
+    pub fn new(context: Arc<GraphQLContext>, display: DisplayFormat) -> Self {
         Self {
+            display,
             state: Arc::new(RwLock::new(ScreensaverState {
                 is_running: true,
+                current: None,
                 current_index: 0,
                 upload_count: 0,
                 interval_seconds: 120,
+                album: None,
+                playlist: None,
+                slide_duration_seconds: None,
+                schedule: None,
+                resume: None,
+                mode: SlideshowMode::Sequential,
+                shuffle: ShuffleOrder::default(),
+                weighted: WeightedOrder::default(),
+                quiet: false,
+                pinned: None,
             })),
             is_running: Arc::new(AtomicBool::new(true)),
-            current_index: Arc::new(AtomicUsize::new(0)),
             context,
             reset_timer_tx: Arc::new(RwLock::new(None)),
+            rgb_device: Arc::new(Mutex::new(RgbDevice::default())),
         }
     }
 
     /// Start the screensaver background task
     pub async fn start(&self) -> Result<()> {
-        info!("Starting screensaver service");
+        info!("Starting {} screensaver service", self.display.as_str());
 
         // Load initial configuration
-        let interval_seconds = ConfigSvc::get_screensaver_interval(&self.context)
+        let interval_seconds = ConfigSvc::get_screensaver_interval(&self.context, self.display)
             .await
             .unwrap_or_else(|e| {
                 warn!("Failed to load screensaver interval, using default 120s: {}", e);
                 120
             });
 
+        let album = ConfigSvc::get_screensaver_album(&self.context, self.display)
+            .await
+            .unwrap_or_else(|e| {
+                warn!("Failed to load screensaver album, using all uploads: {}", e);
+                None
+            });
+        let mode = ConfigSvc::get_screensaver_mode(&self.context, self.display)
+            .await
+            .unwrap_or_else(|e| {
+                warn!("Failed to load screensaver mode, using sequential: {}", e);
+                SlideshowMode::Sequential
+            });
+        let playlist = ConfigSvc::get_screensaver_playlist(&self.context, self.display)
+            .await
+            .unwrap_or_else(|e| {
+                warn!("Failed to load screensaver playlist, using all uploads: {}", e);
+                None
+            });
+        let paused = ConfigSvc::get_screensaver_paused(&self.context, self.display)
+            .await
+            .unwrap_or_else(|e| {
+                warn!("Failed to load screensaver paused state, running: {}", e);
+                false
+            });
+        let current = ConfigSvc::get_screensaver_current_upload(&self.context, self.display)
+            .await
+            .unwrap_or_else(|e| {
+                warn!("Failed to load current screensaver upload, starting over: {}", e);
+                None
+            })
+            .map(|upload| self.current_slide(upload));
+
         // Update state
+        self.is_running.store(!paused, Ordering::Relaxed);
         {
             let mut state = self.state.write().await;
+            state.is_running = !paused;
             state.interval_seconds = interval_seconds;
-            state.upload_count = self.get_rgb_upload_count().await.unwrap_or(0);
+            state.album = album;
+            state.playlist = playlist;
+            state.mode = mode;
+            state.current = current;
+        }
+        let slides = self.get_slides().await.unwrap_or_default();
+        self.refresh_position(&slides).await;
+        let upload_count = slides.len();
+
+        // Whatever was on screen when the server stopped isn't any more
+        let now = chrono::Utc::now().naive_utc();
+        if let Err(e) = HistorySvc::close(&self.context, self.display, now) {
+            warn!("Failed to close display history: {}", e);
         }
 
         info!(
-            "Screensaver initialized with {}s interval, {} RGB uploads available",
+            "{} screensaver initialized with {}s interval, {} uploads available{}",
+            self.display.as_str(),
             interval_seconds,
-            self.get_rgb_upload_count().await.unwrap_or(0)
+            upload_count,
+            if paused { ", paused" } else { "" }
         );
 
         // Start the background task
@@ -94,152 +263,597 @@ impl ScreensaverSvc {
             *tx_guard = Some(reset_tx);
         }
 
+        let mut schedule_check = interval(SCHEDULE_CHECK_INTERVAL);
+        let mut deadline = self.next_deadline().await;
+
         loop {
-            let current_interval = self.state.read().await.interval_seconds;
-            
             tokio::select! {
                 // Normal timer expiration
-                _ = sleep(Duration::from_secs(current_interval)) => {
-                    if self.is_running.load(Ordering::Relaxed) {
-                        if let Err(e) = self.advance_to_next_image().await {
+                _ = sleep_until(deadline) => {
+                    let (quiet, pinned) = {
+                        let state = self.state.read().await;
+                        (state.quiet, state.pinned.is_some())
+                    };
+                    if pinned {
+                        info!("Pinned upload expired, returning to the slideshow");
+                        if let Err(e) = self.end_pin().await {
+                            error!("Failed to return from pinned upload: {}", e);
+                        }
+                    } else if self.is_running.load(Ordering::Relaxed) && !quiet {
+                        if let Err(e) = self.advance(DisplayTrigger::Timer).await {
                             error!("Failed to advance screensaver: {}", e);
                         }
                     }
+                    deadline = self.next_deadline().await;
                 }
                 // Timer reset signal (from new upload or manual advance)
                 _ = reset_rx.recv() => {
-                    // Timer was reset, continue the loop with a fresh timer
-                    continue;
+                    // Timer was reset, start a fresh interval
+                    deadline = self.next_deadline().await;
+                }
+                // Periodic schedule evaluation, independent of the slide timer
+                _ = schedule_check.tick() => {
+                    // Quiet hours apply even while the slideshow is paused
+                    if let Err(e) = self.check_quiet_hours().await {
+                        error!("Failed to evaluate quiet hours: {}", e);
+                    }
+                    if self.is_running.load(Ordering::Relaxed) {
+                        if let Err(e) = self.check_schedule().await {
+                            error!("Failed to evaluate schedule: {}", e);
+                        }
+                    }
                 }
             }
         }
     }
 
-    /// Get count of RGB320x240 uploads
-    async fn get_rgb_upload_count(&self) -> Result<usize> {
-        let mut conn = get_conn(&self.context);
-        let count = uploads::table
-            .filter(uploads::display.eq(DisplayFormat::RGB320x240.as_str()))
-            .count()
-            .get_result::<i64>(&mut conn)
-            .context("Failed to count RGB uploads")?;
-        Ok(count as usize)
+    /// When the current slide should be replaced, honouring pins and per-slide durations
+    async fn next_deadline(&self) -> Instant {
+        let state = self.state.read().await;
+        if let Some(pinned) = state.pinned {
+            let remaining = pinned.until - chrono::Utc::now().naive_utc();
+            return Instant::now() + remaining.to_std().unwrap_or_default();
+        }
+        let seconds = state.slide_duration_seconds.unwrap_or(state.interval_seconds);
+        Instant::now() + Duration::from_secs(seconds)
+    }
+
+    /// Evaluate schedule rules and switch content when the active rule changes
+    async fn check_schedule(&self) -> Result<()> {
+        let rule = ScheduleSvc::active_rule(&self.context, chrono::Utc::now()).await?;
+
+        // Rules with nothing to show on this display don't interrupt the slideshow
+        let rule = match rule {
+            Some(rule) if !self.get_schedule_slides(&rule)?.is_empty() => Some(rule),
+            _ => None,
+        };
+
+        let previous = self.state.read().await.schedule.as_ref().map(|r| r.uuid);
+        if previous == rule.as_ref().map(|r| r.uuid) {
+            // Same rule, but keep its data fresh in case it was edited
+            self.state.write().await.schedule = rule;
+            return Ok(());
+        }
+
+        match &rule {
+            Some(rule) => {
+                info!("Schedule rule {:?} is now active", rule.name);
+                {
+                    let mut state = self.state.write().await;
+                    if state.schedule.is_none() {
+                        let current_index = state.current_index;
+                        state.resume = state.current.map(|current| (current, current_index));
+                    }
+                    state.schedule = Some(rule.clone());
+                }
+
+                let slides = self.get_slides().await?;
+                self.display_at(&slides, 0, DisplayTrigger::Schedule).await;
+            }
+            None => {
+                info!("Schedule ended, returning to the regular slideshow");
+                {
+                    let mut state = self.state.write().await;
+                    state.schedule = None;
+                    let resume = state.resume.take();
+                    state.current = resume.map(|(current, _)| current);
+                    state.current_index = resume.map_or(0, |(_, idx)| idx);
+                }
+
+                let slides = self.get_slides().await?;
+                if !slides.is_empty() {
+                    let position = self.position(&slides).await;
+                    self.display_at(
+                        &slides,
+                        position.current(slides.len()),
+                        DisplayTrigger::Schedule,
+                    )
+                    .await;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Re-evaluate the schedule right away after its rules were changed
+    pub async fn on_schedule_changed(&self) -> Result<()> {
+        if self.is_running.load(Ordering::Relaxed) {
+            self.check_schedule().await?;
+        }
+        Ok(())
     }
 
-    /// Get RGB uploads for slideshow
-    async fn get_rgb_uploads(&self) -> Result<Vec<Upload>> {
+    /// Identify a previously displayed upload, which may since have been trashed or purged
+    fn current_slide(&self, upload: Uuid) -> CurrentSlide {
+        let uploaded_at = uploads::table
+            .filter(uploads::uuid.eq(UUID::from(&upload)))
+            .select(uploads::uploaded_at)
+            .first::<Option<NaiveDateTime>>(&mut get_conn(&self.context))
+            .optional()
+            .unwrap_or_else(|e| {
+                warn!("Failed to look up previously displayed upload: {}", e);
+                None
+            })
+            .flatten();
+
+        CurrentSlide {
+            upload: UUID::from(&upload),
+            uploaded_at,
+        }
+    }
+
+    /// Remember the upload on screen so a restart picks up where the slideshow left off
+    async fn save_current(&self, current: Option<CurrentSlide>) {
+        let upload = current.map(|current| current.upload.into());
+        if let Err(e) =
+            ConfigSvc::set_screensaver_current_upload(&self.context, self.display, upload).await
+        {
+            warn!("Failed to save current screensaver upload: {}", e);
+        }
+    }
+
+    /// Push an upload to this display and keep its device status and history up to date. During
+    /// quiet hours the display stays dark, the upload shows once they end.
+    pub async fn push_to_device(&self, upload: &Upload, trigger: DisplayTrigger) -> Result<()> {
+        if self.state.read().await.quiet {
+            info!("Quiet hours in effect, not pushing image to device");
+            return Ok(());
+        }
+        self.push(upload, trigger).await
+    }
+
+    /// Push an upload to this display even during quiet hours
+    async fn push(&self, upload: &Upload, trigger: DisplayTrigger) -> Result<()> {
+        // Before anything is pushed, a trashed upload says nothing about whether the device is up
+        if upload.deleted_at.is_some() {
+            anyhow::bail!("Upload is in the trash");
+        }
+        let pushed = if self.display == DisplayFormat::RGB320x240 {
+            match decode_rgb(upload) {
+                Ok(image) => self.push_rgb(image.into_raw()).await,
+                Err(e) => Err(e),
+            }
+        } else {
+            push_upload_to_device(upload).await
+        };
+        self.context.events.record_device_push(self.display, &pushed);
+
+        // A failed push leaves the screen in an unknown state, so the previous slide ends too
+        let now = chrono::Utc::now().naive_utc();
+        let logged = match pushed {
+            Ok(()) => HistorySvc::record(&self.context, self.display, upload.uuid, trigger, now),
+            Err(_) => HistorySvc::close(&self.context, self.display, now),
+        };
+        if let Err(e) = logged {
+            warn!("Failed to update display history: {}", e);
+        }
+        pushed
+    }
+
+    /// Push a frame to the RGB device, playing the configured transition from the one on screen
+    async fn push_rgb(&self, frame: Vec<u8>) -> Result<()> {
+        let transition = ConfigSvc::get_transition(&self.context)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to load transition: {}", e))?;
+        let duration = ConfigSvc::get_transition_duration_ms(&self.context)
+            .await
+            .map(Duration::from_millis)
+            .map_err(|e| anyhow::anyhow!("Failed to load transition duration: {}", e))?;
+
+        let mut device = self.rgb_device.lock().await;
+        let previous = device
+            .frame
+            .take()
+            .filter(|previous| is_full_frame(previous) && is_full_frame(&frame));
+        let frames = frame_count(transition, duration, device.frame_time);
+        if let Some(previous) = previous.filter(|_| frames > 0) {
+            let budget = duration / frames as u32;
+            for step in 1..frames {
+                let started = Instant::now();
+                let progress = step as f32 / frames as f32;
+                push_rgb_frame(transition_frame(transition, &previous, &frame, progress)).await?;
+                let elapsed = started.elapsed();
+                device.frame_time = Some(smoothed_frame_time(device.frame_time, elapsed));
+                // Falling this far behind means the device can't keep up, finish with a cut
+                if elapsed > budget * 2 {
+                    warn!(
+                        "RGB device took {}ms for a transition frame, cutting to the next slide",
+                        elapsed.as_millis()
+                    );
+                    break;
+                }
+                sleep(budget.saturating_sub(elapsed)).await;
+            }
+        }
+
+        // Keep measuring on cuts too, so a device that got faster gets transitions again
+        let started = Instant::now();
+        push_rgb_frame(frame.clone()).await?;
+        device.frame_time = Some(smoothed_frame_time(device.frame_time, started.elapsed()));
+        device.frame = Some(frame);
+        Ok(())
+    }
+
+    /// Let subscribers know the slideshow moved on or its settings changed
+    async fn publish_change(&self) {
+        let change = {
+            let state = self.state.read().await;
+            ScreensaverChange {
+                display: self.display,
+                current_upload_uuid: state.current.map(|current| current.upload.into()),
+                pinned_upload_uuid: state.pinned.map(|pinned| pinned.upload.into()),
+                is_running: state.is_running,
+                interval_seconds: state.interval_seconds as i32,
+            }
+        };
+        self.context.events.publish(Event::ScreensaverChanged(change));
+    }
+
+    /// Enter or leave quiet hours, returns whether the quiet state changed
+    async fn check_quiet_hours(&self) -> Result<bool> {
+        // Quiet hours only blank the RGB panel
+        if self.display != DisplayFormat::RGB320x240 {
+            return Ok(false);
+        }
+
+        let quiet = QuietHoursSvc::is_quiet(&self.context, chrono::Utc::now()).await?;
+        let was_quiet = std::mem::replace(&mut self.state.write().await.quiet, quiet);
+        if quiet == was_quiet {
+            return Ok(false);
+        }
+
+        if quiet {
+            info!("Quiet hours started, blanking the display");
+            self.push_quiet_frame().await?;
+        } else {
+            info!("Quiet hours ended, resuming the slideshow");
+            let slides = self.get_slides().await?;
+            if !slides.is_empty() {
+                let position = self.position(&slides).await;
+                self.display_at(
+                    &slides,
+                    position.current(slides.len()),
+                    DisplayTrigger::Schedule,
+                )
+                .await;
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// The blank or dimmed quiet hours frame for the current slide
+    async fn quiet_frame(&self) -> Result<Vec<u8>> {
+        let frame = ConfigSvc::get_quiet_frame(&self.context)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to load quiet frame: {}", e))?;
+        let slides = self.get_slides().await?;
+        let current = match self.position(&slides).await {
+            Position::At(idx) => slides.get(idx),
+            Position::Unknown | Position::Gone(_) => None,
+        };
+
+        QuietHoursSvc::frame(frame, current.map(|slide| &slide.upload))
+    }
+
+    /// Show the blank or dimmed quiet hours frame
+    async fn push_quiet_frame(&self) -> Result<()> {
+        let frame = self.quiet_frame().await?;
+        let pushed = self.push_rgb(frame).await;
+        self.context.events.record_device_push(self.display, &pushed);
+        let now = chrono::Utc::now().naive_utc();
+        if let Err(e) = HistorySvc::close(&self.context, self.display, now) {
+            warn!("Failed to update display history: {}", e);
+        }
+        if let Err(e) = pushed {
+            warn!("Failed to push quiet frame to device (device may be offline): {}", e);
+        }
+        Ok(())
+    }
+
+    /// Re-evaluate quiet hours right away after their settings were changed
+    pub async fn on_quiet_hours_changed(&self) -> Result<()> {
+        let changed = self.check_quiet_hours().await?;
+        // Still quiet, but the frame setting may be different now
+        if !changed && self.state.read().await.quiet {
+            self.push_quiet_frame().await?;
+        }
+        Ok(())
+    }
+
+    /// Base query for the uploads the slideshow may display, restricted to an album if one is set
+    fn slideshow_query(
+        display: DisplayFormat,
+        album: Option<Uuid>,
+    ) -> uploads::BoxedQuery<'static, Sqlite> {
+        let mut query = uploads::table
+            .filter(uploads::display.eq(display.to_string()))
+            .filter(uploads::deleted_at.is_null())
+            .filter(uploads::status.eq(ModerationStatus::Approved.as_str().to_owned()))
+            .into_boxed();
+
+        if let Some(album) = album {
+            query = query.filter(
+                uploads::uuid.eq_any(
+                    album_uploads::table
+                        .filter(album_uploads::album_uuid.eq(UUID::from(&album)))
+                        .select(album_uploads::upload_uuid),
+                ),
+            );
+        }
+
+        query
+    }
+
+    /// Get uploads for this display's slideshow
+    async fn get_uploads(&self) -> Result<Vec<Upload>> {
+        let album = self.state.read().await.album;
         let mut conn = get_conn(&self.context);
-        uploads::table
-            .filter(uploads::display.eq(DisplayFormat::RGB320x240.as_str()))
+        Self::slideshow_query(self.display, album)
             .order_by(uploads::uploaded_at.desc())
             .load::<Upload>(&mut conn)
-            .context("Failed to load RGB uploads")
+            .context("Failed to load uploads")
+    }
+
+    /// Whether an upload belongs on this slideshow's display
+    fn shows(&self, upload: &Upload) -> bool {
+        upload.display.as_deref() == Some(self.display.as_str())
+    }
+
+    /// Get the slides of a playlist for this display, in play order
+    fn get_playlist_slides(&self, playlist: Uuid) -> Result<Vec<Slide>> {
+        Ok(
+            PlaylistSvc::list_slides(&self.context, playlist, self.display)?
+                .into_iter()
+                .map(|(item, upload)| Slide {
+                    upload,
+                    duration_seconds: item.duration_seconds.map(|seconds| seconds as u64),
+                })
+                .collect(),
+        )
+    }
+
+    /// Get the slides a schedule rule shows, empty if its target has nothing for this display
+    fn get_schedule_slides(&self, rule: &ScheduleRule) -> Result<Vec<Slide>> {
+        if let Some(upload_uuid) = rule.upload_uuid {
+            let slides = UploadSvc::get(&self.context, upload_uuid.into())
+                .ok()
+                .filter(|upload| self.shows(upload) && upload.is_approved())
+                .map(|upload| Slide {
+                    upload,
+                    duration_seconds: None,
+                })
+                .into_iter()
+                .collect();
+            return Ok(slides);
+        }
+        if let Some(playlist_uuid) = rule.playlist_uuid {
+            return self.get_playlist_slides(playlist_uuid.into());
+        }
+        Ok(Vec::new())
+    }
+
+    /// Get the slides to cycle through: an active schedule rule, the active playlist in order,
+    /// or every upload for this display
+    async fn get_slides(&self) -> Result<Vec<Slide>> {
+        let (schedule, playlist) = {
+            let state = self.state.read().await;
+            (state.schedule.clone(), state.playlist)
+        };
+        if let Some(rule) = schedule {
+            let slides = self.get_schedule_slides(&rule)?;
+            if !slides.is_empty() {
+                return Ok(slides);
+            }
+        }
+        if let Some(playlist) = playlist {
+            return self.get_playlist_slides(playlist);
+        }
+
+        Ok(self
+            .get_uploads()
+            .await?
+            .into_iter()
+            .map(|upload| Slide {
+                upload,
+                duration_seconds: None,
+            })
+            .collect())
     }
 
     /// Advance to the next image in the slideshow
     pub async fn advance_to_next_image(&self) -> Result<()> {
-        let uploads = self.get_rgb_uploads().await?;
-        
-        if uploads.is_empty() {
-            warn!("No RGB uploads available for slideshow");
+        self.advance(DisplayTrigger::Manual).await
+    }
+
+    async fn advance(&self, trigger: DisplayTrigger) -> Result<()> {
+        let slides = self.get_slides().await?;
+
+        if slides.is_empty() {
+            warn!("No {} uploads available for slideshow", self.display.as_str());
             return Ok(());
         }
 
-        let current_idx = self.current_index.load(Ordering::Relaxed);
-        let next_idx = (current_idx + 1) % uploads.len();
-        
-        if let Some(upload) = uploads.get(next_idx) {
-            info!(
-                "Displaying image {} of {}: {:?}",
-                next_idx + 1,
-                uploads.len(),
-                upload.name.as_deref().unwrap_or("Untitled")
-            );
-
-            // Try to push to device, but don't fail if it's offline
-            if let Err(e) = push_upload_to_device(upload).await {
-                warn!("Failed to push image to device (device may be offline): {}", e);
+        let mode = self.state.read().await.mode;
+        let next_idx = match mode {
+            SlideshowMode::Sequential => self.position(&slides).await.next(slides.len()),
+            SlideshowMode::Shuffle => {
+                let ids = Self::slide_ids(&slides);
+                let next = {
+                    let mut state = self.state.write().await;
+                    state.shuffle.next(&ids, &mut rand::thread_rng())
+                };
+                Self::index_of(&slides, next)
             }
-
-            self.current_index.store(next_idx, Ordering::Relaxed);
-            
-            // Update state
-            {
-                let mut state = self.state.write().await;
-                state.current_index = next_idx;
-                state.upload_count = uploads.len();
+            SlideshowMode::Weighted => {
+                let candidates: Vec<(UUID, i32)> = slides
+                    .iter()
+                    .map(|slide| (slide.upload.uuid, slide.upload.effective_weight()))
+                    .collect();
+                let next = {
+                    let mut state = self.state.write().await;
+                    state.weighted.next(&candidates, &mut rand::thread_rng())
+                };
+                Self::index_of(&slides, next)
             }
+        };
 
-            // Reset the timer after manually advancing
-            self.reset_timer().await;
-        }
+        self.display_at(&slides, next_idx, trigger).await;
 
         Ok(())
     }
 
     /// Go to the previous image
     pub async fn go_to_previous_image(&self) -> Result<()> {
-        let uploads = self.get_rgb_uploads().await?;
-        
-        if uploads.is_empty() {
-            warn!("No RGB uploads available for slideshow");
+        let slides = self.get_slides().await?;
+
+        if slides.is_empty() {
+            warn!("No {} uploads available for slideshow", self.display.as_str());
             return Ok(());
         }
 
-        let current_idx = self.current_index.load(Ordering::Relaxed);
-        let prev_idx = if current_idx == 0 {
-            uploads.len() - 1
-        } else {
-            current_idx - 1
+        let mode = self.state.read().await.mode;
+        let prev_idx = match mode {
+            SlideshowMode::Sequential => self.position(&slides).await.previous(slides.len()),
+            SlideshowMode::Shuffle => {
+                let ids = Self::slide_ids(&slides);
+                let previous = self.state.write().await.shuffle.previous(&ids);
+                Self::index_of(&slides, previous)
+            }
+            SlideshowMode::Weighted => {
+                let ids = Self::slide_ids(&slides);
+                let previous = self.state.write().await.weighted.previous(&ids);
+                Self::index_of(&slides, previous)
+            }
         };
-        
-        if let Some(upload) = uploads.get(prev_idx) {
+
+        self.display_at(&slides, prev_idx, DisplayTrigger::Manual)
+            .await;
+
+        Ok(())
+    }
+
+    /// Upload ids of the slides, in slide order
+    fn slide_ids(slides: &[Slide]) -> Vec<UUID> {
+        slides.iter().map(|slide| slide.upload.uuid).collect()
+    }
+
+    /// Index of the first slide showing `upload`, falling back to the first slide
+    fn index_of(slides: &[Slide], upload: Option<UUID>) -> usize {
+        upload
+            .and_then(|upload| slides.iter().position(|slide| slide.upload.uuid == upload))
+            .unwrap_or(0)
+    }
+
+    /// Push the slide at `idx` to the device, make it the current slide and restart the timer
+    async fn display_at(&self, slides: &[Slide], idx: usize, trigger: DisplayTrigger) {
+        if let Some(slide) = slides.get(idx) {
             info!(
                 "Displaying image {} of {}: {:?}",
-                prev_idx + 1,
-                uploads.len(),
-                upload.name.as_deref().unwrap_or("Untitled")
+                idx + 1,
+                slides.len(),
+                slide.upload.name.as_deref().unwrap_or("Untitled")
             );
 
-            if let Err(e) = push_upload_to_device(upload).await {
+            if let Err(e) = self.push_to_device(&slide.upload, trigger).await {
+                // Don't fail if the device is offline
                 warn!("Failed to push image to device (device may be offline): {}", e);
             }
 
-            self.current_index.store(prev_idx, Ordering::Relaxed);
-            
             // Update state
+            let current = CurrentSlide {
+                upload: slide.upload.uuid,
+                uploaded_at: slide.upload.uploaded_at,
+            };
             {
                 let mut state = self.state.write().await;
-                state.current_index = prev_idx;
-                state.upload_count = uploads.len();
+                state.current = Some(current);
+                state.current_index = idx;
+                state.upload_count = slides.len();
+                state.slide_duration_seconds = slide.duration_seconds;
+                // Anything the slideshow displays replaces a pinned upload
+                state.pinned = None;
             }
+            self.save_current(Some(current)).await;
+            self.publish_change().await;
 
-            // Reset the timer after manually going to previous
+            // Reset the timer so the new image gets a full interval
             self.reset_timer().await;
         }
+    }
 
-        Ok(())
+    /// What the device should be showing right now in the format it is pushed in, for devices
+    /// that pull instead. `None` when the slideshow has nothing to show.
+    pub async fn device_frame(&self) -> Result<Option<Vec<u8>>> {
+        let (quiet, upload) = {
+            let state = self.state.read().await;
+            let upload = state
+                .pinned
+                .map(|pinned| pinned.upload)
+                .or(state.current.map(|current| current.upload));
+            (state.quiet, upload)
+        };
+        if quiet {
+            return self.quiet_frame().await.map(Some);
+        }
+
+        let Some(upload) = upload else {
+            return Ok(None);
+        };
+        let upload = UploadSvc::get(&self.context, upload.into())?;
+        if self.display == DisplayFormat::RGB320x240 {
+            Ok(Some(decode_rgb(&upload)?.into_raw()))
+        } else {
+            Ok(Some(upload.data))
+        }
     }
 
     /// Pause the slideshow
     pub async fn pause(&self) -> Result<()> {
         info!("Pausing screensaver slideshow");
+        ConfigSvc::set_screensaver_paused(&self.context, self.display, true)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to save paused state: {}", e))?;
         self.is_running.store(false, Ordering::Relaxed);
-        
-        let mut state = self.state.write().await;
-        state.is_running = false;
-        
+
+        self.state.write().await.is_running = false;
+        self.publish_change().await;
+
         Ok(())
     }
 
     /// Resume the slideshow
     pub async fn resume(&self) -> Result<()> {
         info!("Resuming screensaver slideshow");
+        ConfigSvc::set_screensaver_paused(&self.context, self.display, false)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to save paused state: {}", e))?;
         self.is_running.store(true, Ordering::Relaxed);
-        
-        let mut state = self.state.write().await;
-        state.is_running = true;
-        
+
+        self.state.write().await.is_running = true;
+        self.publish_change().await;
+
         Ok(())
     }
 
@@ -247,48 +861,295 @@ impl ScreensaverSvc {
     pub async fn set_interval(&self, seconds: u64) -> Result<()> {
         info!("Setting screensaver interval to {}s", seconds);
         
-        ConfigSvc::set_screensaver_interval(&self.context, seconds).await
+        ConfigSvc::set_screensaver_interval(&self.context, self.display, seconds).await
             .map_err(|e| anyhow::anyhow!("Failed to set screensaver interval: {}", e))?;
-        
+
+        self.state.write().await.interval_seconds = seconds;
+        self.publish_change().await;
+
+        Ok(())
+    }
+
+    /// Switch between sequential, shuffled and weighted playback
+    pub async fn set_mode(&self, mode: SlideshowMode) -> Result<()> {
+        info!("Setting screensaver mode to {}", mode.as_str());
+
+        ConfigSvc::set_screensaver_mode(&self.context, self.display, mode)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to set screensaver mode: {}", e))?;
+
         let mut state = self.state.write().await;
-        state.interval_seconds = seconds;
-        
+        state.mode = mode;
+        state.shuffle = ShuffleOrder::default();
+        state.weighted = WeightedOrder::default();
+
+        Ok(())
+    }
+
+    /// Expected share of screen time of each slide in the current slideshow. Only weighted mode
+    /// takes upload weights into account, the other modes give every slide a turn per cycle.
+    pub async fn slide_shares(&self) -> Result<Vec<(Slide, i32, f64)>> {
+        let slides = self.get_slides().await?;
+        let (mode, interval_seconds) = {
+            let state = self.state.read().await;
+            (state.mode, state.interval_seconds)
+        };
+
+        let weights: Vec<i32> = slides
+            .iter()
+            .map(|slide| match mode {
+                SlideshowMode::Weighted => slide.upload.effective_weight(),
+                SlideshowMode::Sequential | SlideshowMode::Shuffle => 1,
+            })
+            .collect();
+        let shares = screen_time_shares(
+            &slides
+                .iter()
+                .zip(&weights)
+                .map(|(slide, weight)| {
+                    (*weight, slide.duration_seconds.unwrap_or(interval_seconds))
+                })
+                .collect::<Vec<_>>(),
+        );
+
+        Ok(slides
+            .into_iter()
+            .zip(weights)
+            .zip(shares)
+            .map(|((slide, weight), share)| (slide, weight, share))
+            .collect())
+    }
+
+    /// Restrict the slideshow to an album, or pass `None` to cycle through every upload
+    pub async fn set_album(&self, album: Option<Uuid>) -> Result<()> {
+        info!("Setting screensaver album to {:?}", album);
+
+        ConfigSvc::set_screensaver_album(&self.context, self.display, album)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to set screensaver album: {}", e))?;
+
+        self.state.write().await.album = album;
+        self.restart_from_beginning().await
+    }
+
+    /// Run a playlist, or pass `None` to return to the regular slideshow
+    pub async fn set_playlist(&self, playlist: Option<Uuid>) -> Result<()> {
+        info!("Setting screensaver playlist to {:?}", playlist);
+
+        ConfigSvc::set_screensaver_playlist(&self.context, self.display, playlist)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to set screensaver playlist: {}", e))?;
+
+        self.state.write().await.playlist = playlist;
+        self.restart_from_beginning().await
+    }
+
+    /// Show the first slide after the set of slides changed
+    async fn restart_from_beginning(&self) -> Result<()> {
+        let slides = self.get_slides().await?;
+        if slides.is_empty() {
+            warn!("No {} uploads available for slideshow", self.display.as_str());
+
+            {
+                let mut state = self.state.write().await;
+                state.current = None;
+                state.current_index = 0;
+                state.upload_count = 0;
+                state.slide_duration_seconds = None;
+            }
+            self.save_current(None).await;
+            self.publish_change().await;
+        } else {
+            self.display_at(&slides, 0, DisplayTrigger::Manual).await;
+        }
+
         Ok(())
     }
 
+    /// Display format this slideshow drives
+    pub fn display(&self) -> DisplayFormat {
+        self.display
+    }
+
     /// Get current screensaver state
     pub async fn get_state(&self) -> ScreensaverState {
         self.state.read().await.clone()
     }
 
-    /// Handle new upload - immediately display if it's RGB
+    /// Handle new upload - immediately display if it's for this display
     pub async fn on_new_upload(&self, upload: &Upload) -> Result<()> {
-        if upload.display.as_deref() == Some(DisplayFormat::RGB320x240.as_str()) {
-            info!("New RGB upload received, displaying immediately: {:?}", 
-                  upload.name.as_deref().unwrap_or("Untitled"));
-            
-            // Push to device immediately
-            if let Err(e) = push_upload_to_device(upload).await {
-                warn!("Failed to push new upload to device: {}", e);
+        if self.shows(upload) {
+            info!(
+                "New {} upload received, displaying immediately: {:?}",
+                self.display.as_str(),
+                upload.name.as_deref().unwrap_or("Untitled")
+            );
+
+            // UploadSvc::create already pushed it through this slideshow, carry on from it
+            self.make_current(upload).await?;
+
+            // Reset the timer so the next image won't appear for a full interval
+            self.reset_timer().await;
+        }
+
+        Ok(())
+    }
+
+    /// Show an upload right away. With `pin_seconds` the slideshow holds it for that long and
+    /// then returns to where it was, otherwise it carries on from the upload.
+    pub async fn show_upload(&self, upload: &Upload, pin_seconds: Option<u64>) -> Result<()> {
+        if !self.shows(upload) {
+            anyhow::bail!("Upload is not for the {} display", self.display.as_str());
+        }
+        if !upload.is_approved() {
+            anyhow::bail!("Upload hasn't been approved by a moderator");
+        }
+        info!(
+            "Showing {:?} on request{}",
+            upload.name.as_deref().unwrap_or("Untitled"),
+            pin_seconds.map_or(String::new(), |seconds| format!(" for {seconds}s"))
+        );
+
+        // Pinned uploads are shown during quiet hours too, the others wait for them to end
+        if pin_seconds.is_some() {
+            self.push(upload, DisplayTrigger::Manual).await?;
+        } else {
+            self.push_to_device(upload, DisplayTrigger::Manual).await?;
+        }
+
+        match pin_seconds {
+            Some(seconds) => {
+                let until = chrono::Utc::now().naive_utc()
+                    + chrono::Duration::seconds(seconds.try_into().unwrap_or(i64::MAX));
+                self.state.write().await.pinned = Some(PinnedUpload {
+                    upload: upload.uuid,
+                    until,
+                });
+                self.publish_change().await;
             }
-            
-            // Update upload count and reset to show the new image first
-            let upload_count = self.get_rgb_upload_count().await.unwrap_or(0);
-            self.current_index.store(0, Ordering::Relaxed);
-            
+            None => self.make_current(upload).await?,
+        }
+
+        self.reset_timer().await;
+        Ok(())
+    }
+
+    /// Go back to the slideshow once a pinned upload's time is up or it was deleted
+    async fn end_pin(&self) -> Result<()> {
+        let slides = self.get_slides().await?;
+        if slides.is_empty() {
+            self.state.write().await.pinned = None;
+            self.publish_change().await;
+            return Ok(());
+        }
+
+        let position = self.position(&slides).await;
+        self.display_at(
+            &slides,
+            position.current(slides.len()),
+            DisplayTrigger::Timer,
+        )
+        .await;
+        // The pinned upload was pushed regardless of quiet hours, go dark again
+        if self.state.read().await.quiet {
+            self.push_quiet_frame().await?;
+        }
+        Ok(())
+    }
+
+    /// Continue the slideshow from an upload that was just pushed outside of it. A playlist or
+    /// album the upload isn't part of keeps its place.
+    async fn make_current(&self, upload: &Upload) -> Result<()> {
+        self.state.write().await.pinned = None;
+        let slides = self.get_slides().await?;
+        if let Some(idx) = slides.iter().position(|slide| slide.upload.uuid == upload.uuid) {
+            let current = CurrentSlide {
+                upload: upload.uuid,
+                uploaded_at: upload.uploaded_at,
+            };
             {
                 let mut state = self.state.write().await;
-                state.current_index = 0;
-                state.upload_count = upload_count;
+                state.current = Some(current);
+                state.current_index = idx;
+                state.slide_duration_seconds = slides[idx].duration_seconds;
             }
+            self.save_current(Some(current)).await;
+        }
+        self.refresh_position(&slides).await;
+        self.publish_change().await;
+        Ok(())
+    }
 
-            // Reset the timer so the next image won't appear for a full interval
-            self.reset_timer().await;
+    /// Take an upload that went to the trash out of the slideshow. If it is pinned the slideshow
+    /// returns to where it was, if it is on screen the next slide is shown right away, otherwise
+    /// the position is counted again without it.
+    pub async fn on_upload_deleted(&self, upload: &Upload) -> Result<()> {
+        if !self.shows(upload) {
+            return Ok(());
         }
-        
+
+        let (pinned, on_screen) = {
+            let state = self.state.read().await;
+            (
+                state
+                    .pinned
+                    .is_some_and(|pinned| pinned.upload == upload.uuid),
+                state
+                    .current
+                    .is_some_and(|current| current.upload == upload.uuid),
+            )
+        };
+        if pinned {
+            // Return to the slideshow right away instead of when the pin would have expired
+            info!("Pinned upload was deleted, returning to the slideshow");
+            return self.end_pin().await;
+        }
+        if on_screen {
+            // Show the image that follows it, as if its time was up
+            info!("Displayed upload was deleted, skipping ahead");
+            return self.advance_to_next_image().await;
+        }
+
+        let slides = self.get_slides().await?;
+        self.refresh_position(&slides).await;
         Ok(())
     }
 
+    /// Handle restored upload - the current image stays on screen
+    pub async fn on_upload_restored(&self, upload: &Upload) -> Result<()> {
+        if !self.shows(upload) {
+            return Ok(());
+        }
+
+        let slides = self.get_slides().await?;
+        self.refresh_position(&slides).await;
+        Ok(())
+    }
+
+    /// Where the current upload sits in `slides`
+    async fn position(&self, slides: &[Slide]) -> Position {
+        let state = self.state.read().await;
+        // Only the regular slideshow is ordered by `uploaded_at desc`
+        let follows_upload_order = state.schedule.is_none() && state.playlist.is_none();
+        locate(
+            state.current,
+            state.current_index,
+            follows_upload_order,
+            slides,
+        )
+    }
+
+    /// Update the slide count and the current upload's index after the set of slides changed
+    async fn refresh_position(&self, slides: &[Slide]) {
+        let position = self.position(slides).await;
+
+        let mut state = self.state.write().await;
+        state.upload_count = slides.len();
+        if let Position::At(idx) = position {
+            state.current_index = idx;
+        }
+    }
+
     /// Reset the slideshow timer
     async fn reset_timer(&self) {
         if let Some(tx) = &*self.reset_timer_tx.read().await {
@@ -301,11 +1162,146 @@ impl ScreensaverSvc {
 impl Clone for ScreensaverSvc {
     fn clone(&self) -> Self {
         Self {
+            display: self.display,
             state: Arc::clone(&self.state),
             is_running: Arc::clone(&self.is_running),
-            current_index: Arc::clone(&self.current_index),
             context: Arc::clone(&self.context),
             reset_timer_tx: Arc::clone(&self.reset_timer_tx),
+            rgb_device: Arc::clone(&self.rgb_device),
+        }
+    }
+}
+
+/// Find the current upload in `slides`. An upload can appear more than once in a playlist, the
+/// occurrence closest to `hint` wins. When it is gone, its place in the regular `uploaded_at desc`
+/// order is worked out from its upload time, other slideshows fall back to `hint`.
+fn locate(
+    current: Option<CurrentSlide>,
+    hint: usize,
+    follows_upload_order: bool,
+    slides: &[Slide],
+) -> Position {
+    let Some(current) = current else {
+        return Position::Unknown;
+    };
+
+    let closest = slides
+        .iter()
+        .enumerate()
+        .filter(|(_, slide)| slide.upload.uuid == current.upload)
+        .min_by_key(|(idx, _)| idx.abs_diff(hint))
+        .map(|(idx, _)| idx);
+    if let Some(idx) = closest {
+        return Position::At(idx);
+    }
+
+    if follows_upload_order && current.uploaded_at.is_some() {
+        let newer = slides
+            .iter()
+            .filter(|slide| slide.upload.uploaded_at > current.uploaded_at)
+            .count();
+        return Position::Gone(newer);
+    }
+    Position::Gone(hint.min(slides.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn slide(day: u32) -> Slide {
+        Slide {
+            upload: Upload {
+                uuid: UUID::random(),
+                message: None,
+                data: Vec::new(),
+                public: true,
+                uploaded_at: NaiveDate::from_ymd_opt(2026, 1, day)
+                    .and_then(|date| date.and_hms_opt(12, 0, 0)),
+                name: None,
+                display: Some(DisplayFormat::RGB320x240.to_string()),
+                deleted_at: None,
+                weight: 1,
+                favorite: false,
+                owner_uuid: None,
+                status: "approved".to_owned(),
+                moderated_by: None,
+                moderated_at: None,
+                rejection_reason: None,
+                content_hash: None,
+            },
+            duration_seconds: None,
         }
     }
-}
\ No newline at end of file
+
+    fn current(slide: &Slide) -> Option<CurrentSlide> {
+        Some(CurrentSlide {
+            upload: slide.upload.uuid,
+            uploaded_at: slide.upload.uploaded_at,
+        })
+    }
+
+    #[test]
+    fn it_follows_the_current_upload_when_newer_ones_arrive() {
+        // Newest first, like the regular slideshow
+        let mut slides = vec![slide(3), slide(2), slide(1)];
+        let on_screen = current(&slides[1]);
+
+        slides.insert(0, slide(4));
+        let position = locate(on_screen, 1, true, &slides);
+        assert_eq!(position, Position::At(2));
+        assert_eq!(position.next(slides.len()), 3);
+        assert_eq!(position.previous(slides.len()), 1);
+    }
+
+    #[test]
+    fn it_continues_after_a_removed_upload() {
+        let mut slides = vec![slide(4), slide(3), slide(2), slide(1)];
+        let on_screen = current(&slides[2]);
+
+        // The upload on screen and one before it both disappear
+        slides.remove(2);
+        slides.remove(0);
+        let position = locate(on_screen, 2, true, &slides);
+        assert_eq!(position, Position::Gone(1));
+        let next = &slides[position.next(slides.len())];
+        let previous = &slides[position.previous(slides.len())];
+        assert_eq!(next.upload.uploaded_at, slide(1).upload.uploaded_at);
+        assert_eq!(previous.upload.uploaded_at, slide(3).upload.uploaded_at);
+    }
+
+    #[test]
+    fn it_picks_the_closest_repeat_in_a_playlist() {
+        let a = slide(1);
+        let b = slide(2);
+        let slides = vec![a.clone(), b.clone(), a.clone(), b];
+
+        assert_eq!(locate(current(&a), 2, false, &slides), Position::At(2));
+        assert_eq!(locate(current(&a), 0, false, &slides), Position::At(0));
+    }
+
+    #[tokio::test]
+    async fn it_keeps_the_display_dark_during_quiet_hours() {
+        let context = GraphQLContext::in_memory();
+        let screensaver = ScreensaverSvc::new(Arc::new(context), DisplayFormat::RGB320x240);
+        screensaver.state.write().await.quiet = true;
+
+        let upload = slide(1).upload;
+        assert!(screensaver
+            .push_to_device(&upload, DisplayTrigger::NewUpload)
+            .await
+            .is_ok());
+        // Nothing reached the device, so its status is still unknown
+        assert!(screensaver.context.events.device_statuses().is_empty());
+    }
+
+    #[test]
+    fn it_wraps_around_the_ends() {
+        assert_eq!(Position::At(2).next(3), 0);
+        assert_eq!(Position::At(0).previous(3), 2);
+        assert_eq!(Position::Gone(3).next(3), 0);
+        assert_eq!(Position::Unknown.next(3), 0);
+        assert_eq!(Position::Unknown.previous(3), 2);
+    }
+}
@@ -0,0 +1,70 @@
+use crate::{
+    context::GraphQLContext,
+    db::get_conn,
+    models::{Session, User},
+    schema::{sessions, users},
+    svc::secret_digest,
+};
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+
+/// Name of the cookie holding the session token
+pub const SESSION_COOKIE: &str = "session";
+
+/// How long a login lasts
+pub const SESSION_DAYS: i64 = 30;
+
+pub struct SessionSvc {}
+
+impl SessionSvc {
+    /// Start a session for `user`, returns the token for the session cookie. Only a digest of
+    /// the token is stored.
+    pub fn create(context: &GraphQLContext, user: &User) -> Result<String> {
+        Self::purge_expired(context)?;
+
+        let now = Utc::now().naive_utc();
+        let token = URL_SAFE_NO_PAD.encode(rand::random::<[u8; 32]>());
+        let session = Session {
+            token_hash: secret_digest(&token),
+            user_uuid: user.uuid,
+            created_at: now,
+            expires_at: now + Duration::days(SESSION_DAYS),
+        };
+
+        diesel::insert_into(sessions::table)
+            .values(&session)
+            .execute(&mut get_conn(context))
+            .context("Could not create session")?;
+        Ok(token)
+    }
+
+    /// The user a session token belongs to, `None` if it is unknown or expired
+    pub fn user(context: &GraphQLContext, token: &str) -> Result<Option<User>> {
+        sessions::table
+            .inner_join(users::table)
+            .filter(sessions::token_hash.eq(secret_digest(token)))
+            .filter(sessions::expires_at.gt(Utc::now().naive_utc()))
+            .select(User::as_select())
+            .first::<User>(&mut get_conn(context))
+            .optional()
+            .context("Could not load session")
+    }
+
+    pub fn delete(context: &GraphQLContext, token: &str) -> Result<()> {
+        diesel::delete(sessions::table)
+            .filter(sessions::token_hash.eq(secret_digest(token)))
+            .execute(&mut get_conn(context))
+            .context("Could not delete session")?;
+        Ok(())
+    }
+
+    /// Drop sessions that ran out, returns how many were removed
+    pub fn purge_expired(context: &GraphQLContext) -> Result<usize> {
+        diesel::delete(sessions::table)
+            .filter(sessions::expires_at.le(Utc::now().naive_utc()))
+            .execute(&mut get_conn(context))
+            .context("Could not purge expired sessions")
+    }
+}
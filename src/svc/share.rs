@@ -0,0 +1,225 @@
+use crate::{
+    config::ConfigSvc,
+    context::GraphQLContext,
+    db::get_conn,
+    get_env,
+    models::{ShareLink, Upload, User},
+    schema::share_links,
+    uuid::UUID,
+};
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use diesel::prelude::*;
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+use uuid::Uuid;
+
+/// Longest a share link can stay valid, 30 days
+pub const MAX_SHARE_SECONDS: i32 = 30 * 24 * 60 * 60;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Query parameters of a shared image URL
+#[derive(Debug, Deserialize)]
+pub struct ShareParams {
+    pub share: Uuid,
+    pub expires: i64,
+    pub signature: String,
+}
+
+pub struct ShareSvc {}
+
+impl ShareSvc {
+    pub fn get(context: &GraphQLContext, link_uuid: Uuid) -> Result<ShareLink> {
+        share_links::table
+            .filter(share_links::uuid.eq(UUID::from(&link_uuid)))
+            .first(&mut get_conn(context))
+            .context("Could not find share link")
+    }
+    /// Links to an upload, newest first, including expired and revoked ones
+    pub fn list_for_upload(context: &GraphQLContext, upload_uuid: Uuid) -> Result<Vec<ShareLink>> {
+        share_links::table
+            .filter(share_links::upload_uuid.eq(UUID::from(&upload_uuid)))
+            .order_by(share_links::created_at.desc())
+            .load::<ShareLink>(&mut get_conn(context))
+            .context("Could not load share links")
+    }
+
+    /// Share `upload` for `expires_in` seconds
+    pub fn create(
+        context: &GraphQLContext,
+        upload: &Upload,
+        user: &User,
+        expires_in: i32,
+    ) -> Result<ShareLink> {
+        if !(1..=MAX_SHARE_SECONDS).contains(&expires_in) {
+            anyhow::bail!(
+                "Share links must expire within {} days",
+                MAX_SHARE_SECONDS / (24 * 60 * 60)
+            );
+        }
+        // Sharing would get around the moderation queue
+        if !upload.is_approved() {
+            anyhow::bail!("Only approved uploads can be shared");
+        }
+
+        let now = chrono::Utc::now().naive_utc();
+        let link = ShareLink {
+            uuid: UUID::random(),
+            upload_uuid: upload.uuid,
+            created_by: Some(user.uuid),
+            expires_at: now + chrono::Duration::seconds(expires_in.into()),
+            revoked_at: None,
+            created_at: now,
+        };
+
+        diesel::insert_into(share_links::table)
+            .values(&link)
+            .execute(&mut get_conn(context))
+            .context("Could not create share link")?;
+        Ok(link)
+    }
+
+    /// Stop a link from working. Revoking it again keeps the time it was first revoked.
+    pub fn revoke(context: &GraphQLContext, link_uuid: Uuid) -> Result<ShareLink> {
+        diesel::update(share_links::table)
+            .filter(share_links::uuid.eq(UUID::from(&link_uuid)))
+            .filter(share_links::revoked_at.is_null())
+            .set(share_links::revoked_at.eq(chrono::Utc::now().naive_utc()))
+            .execute(&mut get_conn(context))
+            .context("Could not revoke share link")?;
+
+        Self::get(context, link_uuid)
+    }
+
+    /// Signed URL of the link's image, relative unless `PUBLIC_URL` is set
+    pub async fn url(context: &GraphQLContext, link: &ShareLink) -> Result<String> {
+        let secret = Self::secret(context).await?;
+        let upload_uuid = Uuid::from(link.upload_uuid);
+        let link_uuid = Uuid::from(link.uuid);
+        let expires = link.expires_at.and_utc().timestamp();
+        Ok(format!(
+            "{}/api/v1/uploads/{}/image?share={}&expires={}&signature={}",
+            get_env("PUBLIC_URL", "").trim_end_matches('/'),
+            upload_uuid,
+            link_uuid,
+            expires,
+            sign(&secret, upload_uuid, link_uuid, expires)
+        ))
+    }
+
+    /// Whether `params` are a correctly signed link to the upload that is neither expired nor
+    /// revoked
+    pub async fn verify(
+        context: &GraphQLContext,
+        upload_uuid: Uuid,
+        params: &ShareParams,
+    ) -> Result<bool> {
+        let secret = Self::secret(context).await?;
+        if !verify_signature(
+            &secret,
+            upload_uuid,
+            params.share,
+            params.expires,
+            &params.signature,
+        ) {
+            return Ok(false);
+        }
+
+        let link = share_links::table
+            .filter(share_links::uuid.eq(UUID::from(&params.share)))
+            .filter(share_links::upload_uuid.eq(UUID::from(&upload_uuid)))
+            .first::<ShareLink>(&mut get_conn(context))
+            .optional()
+            .context("Could not load share link")?;
+        Ok(link.is_some_and(|link| link.is_active(chrono::Utc::now().naive_utc())))
+    }
+
+    async fn secret(context: &GraphQLContext) -> Result<String> {
+        ConfigSvc::get_share_link_secret(context)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to load share link secret: {}", e))
+    }
+}
+
+/// HMAC over everything the URL of a link says about it
+fn mac(secret: &str, upload_uuid: Uuid, link_uuid: Uuid, expires: i64) -> HmacSha256 {
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(format!("{upload_uuid}:{link_uuid}:{expires}").as_bytes());
+    mac
+}
+
+fn sign(secret: &str, upload_uuid: Uuid, link_uuid: Uuid, expires: i64) -> String {
+    URL_SAFE_NO_PAD.encode(
+        mac(secret, upload_uuid, link_uuid, expires)
+            .finalize()
+            .into_bytes(),
+    )
+}
+
+/// Compares in constant time, so the signature can't be guessed byte by byte
+fn verify_signature(
+    secret: &str,
+    upload_uuid: Uuid,
+    link_uuid: Uuid,
+    expires: i64,
+    signature: &str,
+) -> bool {
+    let Ok(signature) = URL_SAFE_NO_PAD.decode(signature) else {
+        return false;
+    };
+    mac(secret, upload_uuid, link_uuid, expires)
+        .verify_slice(&signature)
+        .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_verifies_only_unaltered_links() {
+        let upload_uuid = Uuid::now_v7();
+        let link_uuid = Uuid::now_v7();
+        let signature = sign("secret", upload_uuid, link_uuid, 1_800_000_000);
+
+        assert!(verify_signature(
+            "secret",
+            upload_uuid,
+            link_uuid,
+            1_800_000_000,
+            &signature
+        ));
+        // A later expiry, another upload or another key break the signature
+        assert!(!verify_signature(
+            "secret",
+            upload_uuid,
+            link_uuid,
+            1_900_000_000,
+            &signature
+        ));
+        assert!(!verify_signature(
+            "secret",
+            Uuid::now_v7(),
+            link_uuid,
+            1_800_000_000,
+            &signature
+        ));
+        assert!(!verify_signature(
+            "other",
+            upload_uuid,
+            link_uuid,
+            1_800_000_000,
+            &signature
+        ));
+        assert!(!verify_signature(
+            "secret",
+            upload_uuid,
+            link_uuid,
+            1_800_000_000,
+            "not base64!"
+        ));
+    }
+}
@@ -0,0 +1,193 @@
+use crate::uuid::UUID;
+use rand::{seq::SliceRandom, Rng};
+
+/// Playback order for shuffle mode. Every upload is played once per round in random order. The
+/// previous round is kept so stepping backwards retraces what was actually shown.
+#[derive(Debug, Clone, Default)]
+pub struct ShuffleOrder {
+    order: Vec<UUID>,
+    /// Index into `order` of the upload on screen, `None` before the first one
+    position: Option<usize>,
+    /// Index into `order` where the current round starts
+    round_start: usize,
+}
+
+impl ShuffleOrder {
+    /// The upload currently on screen
+    pub fn current(&self) -> Option<UUID> {
+        self.position.and_then(|position| self.order.get(position).copied())
+    }
+
+    /// Move forward, starting a freshly shuffled round once the current one is exhausted
+    pub fn next(&mut self, ids: &[UUID], rng: &mut impl Rng) -> Option<UUID> {
+        self.sync(ids, rng);
+
+        let next = self.position.map_or(0, |position| position + 1);
+        if next < self.order.len() {
+            self.position = Some(next);
+            return self.current();
+        }
+
+        self.start_round(ids, rng);
+        self.current()
+    }
+
+    /// Step back through what was shown, skipping uploads that are no longer available
+    pub fn previous(&mut self, ids: &[UUID]) -> Option<UUID> {
+        let mut position = self.position?;
+        while position > 0 {
+            position -= 1;
+            if ids.contains(&self.order[position]) {
+                self.position = Some(position);
+                return self.current();
+            }
+        }
+
+        // Nothing earlier is retained, stay where we are
+        self.current()
+    }
+
+    /// Forget uploads that disappeared and slot new ones into the rest of the current round
+    fn sync(&mut self, ids: &[UUID], rng: &mut impl Rng) {
+        let mut idx = 0;
+        while idx < self.order.len() {
+            if ids.contains(&self.order[idx]) {
+                idx += 1;
+                continue;
+            }
+
+            self.order.remove(idx);
+            if idx < self.round_start {
+                self.round_start -= 1;
+            }
+            // When the upload on screen goes away, step back so `next` lands on its successor
+            self.position = match self.position {
+                Some(position) if idx <= position => position.checked_sub(1),
+                position => position,
+            };
+        }
+
+        // Don't start a partial round from nothing, `start_round` will cover everything
+        if self.order.is_empty() {
+            self.round_start = 0;
+            return;
+        }
+
+        let insert_from = self
+            .position
+            .map_or(self.round_start, |position| position + 1)
+            .max(self.round_start);
+        for id in ids {
+            if !self.order[self.round_start..].contains(id) {
+                let at = rng.gen_range(insert_from..=self.order.len());
+                self.order.insert(at, *id);
+            }
+        }
+    }
+
+    /// Append a new round and move to its first upload, dropping rounds before the previous one
+    fn start_round(&mut self, ids: &[UUID], rng: &mut impl Rng) {
+        let mut round: Vec<UUID> = Vec::with_capacity(ids.len());
+        for id in ids {
+            if !round.contains(id) {
+                round.push(*id);
+            }
+        }
+        if round.is_empty() {
+            return;
+        }
+        round.shuffle(rng);
+
+        // Never show the same upload twice in a row across the round boundary
+        if round.len() > 1 && Some(round[0]) == self.current() {
+            let swap_with = rng.gen_range(1..round.len());
+            round.swap(0, swap_with);
+        }
+
+        self.order.drain(..self.round_start);
+        self.round_start = self.order.len();
+        self.order.extend(round);
+        self.position = Some(self.round_start);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn ids(count: usize) -> Vec<UUID> {
+        (0..count).map(|_| UUID::random()).collect()
+    }
+
+    #[test]
+    fn it_plays_every_upload_once_per_round() {
+        let ids = ids(8);
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut order = ShuffleOrder::default();
+
+        for _ in 0..3 {
+            let mut round: Vec<UUID> = (0..ids.len())
+                .map(|_| order.next(&ids, &mut rng).unwrap())
+                .collect();
+            round.sort_by_key(|id| id.0);
+            let mut expected = ids.clone();
+            expected.sort_by_key(|id| id.0);
+            assert_eq!(round, expected);
+        }
+    }
+
+    #[test]
+    fn it_never_repeats_across_rounds() {
+        let ids = ids(3);
+        for seed in 0..50 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut order = ShuffleOrder::default();
+            let mut last = None;
+            for _ in 0..30 {
+                let next = order.next(&ids, &mut rng);
+                assert_ne!(next, last);
+                last = next;
+            }
+        }
+    }
+
+    #[test]
+    fn it_goes_back_through_shown_uploads() {
+        let ids = ids(4);
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut order = ShuffleOrder::default();
+
+        // Play into the second round so going back crosses the boundary
+        let shown: Vec<UUID> = (0..6).map(|_| order.next(&ids, &mut rng).unwrap()).collect();
+        for expected in shown.iter().rev().skip(1) {
+            assert_eq!(order.previous(&ids), Some(*expected));
+        }
+
+        // Going forward again replays the same order
+        for expected in shown.iter().skip(1) {
+            assert_eq!(order.next(&ids, &mut rng), Some(*expected));
+        }
+    }
+
+    #[test]
+    fn it_handles_removed_and_added_uploads() {
+        let mut ids = ids(5);
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut order = ShuffleOrder::default();
+
+        let first = order.next(&ids, &mut rng).unwrap();
+        ids.retain(|id| *id != first);
+        let added = UUID::random();
+        ids.push(added);
+
+        let rest: Vec<UUID> = (0..ids.len())
+            .map(|_| order.next(&ids, &mut rng).unwrap())
+            .collect();
+        assert!(!rest.contains(&first));
+        assert!(rest.contains(&added));
+        for id in &ids {
+            assert_eq!(rest.iter().filter(|shown| *shown == id).count(), 1);
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use crate::{
+    context::GraphQLContext,
+    db::get_conn,
+    models::UploadTag,
+    schema::{upload_tags, uploads},
+    uuid::UUID,
+};
+use anyhow::{Context, Result};
+use diesel::prelude::*;
+use uuid::Uuid;
+
+pub struct TagSvc {}
+
+impl TagSvc {
+    /// Tags are matched case-insensitively, so store them trimmed and lowercased
+    pub fn normalize(tag: &str) -> String {
+        tag.trim().to_lowercase()
+    }
+    pub fn list(context: &GraphQLContext) -> Result<Vec<String>> {
+        upload_tags::table
+            .inner_join(uploads::table)
+            .filter(uploads::deleted_at.is_null())
+            .select(upload_tags::tag)
+            .distinct()
+            .order_by(upload_tags::tag.asc())
+            .load::<String>(&mut get_conn(context))
+            .context("Could not load tags")
+    }
+    pub fn list_for_upload(context: &GraphQLContext, upload_uuid: UUID) -> Result<Vec<String>> {
+        upload_tags::table
+            .filter(upload_tags::upload_uuid.eq(upload_uuid))
+            .select(upload_tags::tag)
+            .order_by(upload_tags::tag.asc())
+            .load::<String>(&mut get_conn(context))
+            .context("Could not load upload tags")
+    }
+    pub fn tag_upload(context: &GraphQLContext, upload_uuid: Uuid, tags: &[String]) -> Result<()> {
+        let entries: Vec<UploadTag> = tags
+            .iter()
+            .map(|tag| Self::normalize(tag))
+            .filter(|tag| !tag.is_empty())
+            .map(|tag| UploadTag {
+                upload_uuid: UUID::from(&upload_uuid),
+                tag,
+            })
+            .collect();
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        diesel::insert_or_ignore_into(upload_tags::table)
+            .values(&entries)
+            .execute(&mut get_conn(context))
+            .context("Could not tag upload")?;
+
+        Ok(())
+    }
+    pub fn untag_upload(context: &GraphQLContext, upload_uuid: Uuid, tag: &str) -> Result<()> {
+        diesel::delete(upload_tags::table)
+            .filter(upload_tags::upload_uuid.eq(UUID::from(&upload_uuid)))
+            .filter(upload_tags::tag.eq(Self::normalize(tag)))
+            .execute(&mut get_conn(context))
+            .context("Could not untag upload")?;
+
+        Ok(())
+    }
+}
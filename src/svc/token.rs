@@ -0,0 +1,157 @@
+use crate::{
+    context::GraphQLContext,
+    db::get_conn,
+    models::{ApiScope, ApiToken, User},
+    schema::{api_tokens, users},
+    uuid::UUID,
+};
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use diesel::prelude::*;
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+use uuid::Uuid;
+
+/// Start of every API token, makes them easy to spot in logs and secret scanners
+const TOKEN_PREFIX: &str = "imt_";
+
+pub struct ApiTokenSvc {}
+
+impl ApiTokenSvc {
+    pub fn get(context: &GraphQLContext, token_uuid: Uuid) -> Result<ApiToken> {
+        api_tokens::table
+            .filter(api_tokens::uuid.eq(UUID::from(&token_uuid)))
+            .first(&mut get_conn(context))
+            .context("Could not find API token")
+    }
+    pub fn list_for_user(context: &GraphQLContext, user_uuid: Uuid) -> Result<Vec<ApiToken>> {
+        api_tokens::table
+            .filter(api_tokens::user_uuid.eq(UUID::from(&user_uuid)))
+            .order_by(api_tokens::created_at.desc())
+            .load::<ApiToken>(&mut get_conn(context))
+            .context("Could not load API tokens")
+    }
+
+    /// Create a token for `user`, returns it along with the secret token string. Only a digest of
+    /// the secret is stored, it can't be shown again.
+    pub fn create(
+        context: &GraphQLContext,
+        user: &User,
+        name: &str,
+        scopes: &[ApiScope],
+    ) -> Result<(ApiToken, String)> {
+        let name = name.trim();
+        if name.is_empty() {
+            anyhow::bail!("Token name cannot be empty");
+        }
+        if scopes.is_empty() {
+            anyhow::bail!("A token needs at least one scope");
+        }
+
+        let mut scope_names: Vec<&str> = scopes.iter().map(|scope| scope.as_str()).collect();
+        scope_names.sort_unstable();
+        scope_names.dedup();
+
+        let secret = URL_SAFE_NO_PAD.encode(rand::random::<[u8; 32]>());
+        let token = ApiToken {
+            uuid: UUID::random(),
+            user_uuid: user.uuid,
+            name: name.to_owned(),
+            token_hash: secret_digest(&secret),
+            scopes: scope_names.join(","),
+            created_at: chrono::Utc::now().naive_utc(),
+            last_used_at: None,
+        };
+
+        diesel::insert_into(api_tokens::table)
+            .values(&token)
+            .execute(&mut get_conn(context))
+            .context("Could not create API token")?;
+
+        let token_string = format!("{}{}_{}", TOKEN_PREFIX, token.uuid.0.simple(), secret);
+        Ok((token, token_string))
+    }
+
+    pub fn delete(context: &GraphQLContext, token_uuid: Uuid) -> Result<()> {
+        diesel::delete(api_tokens::table)
+            .filter(api_tokens::uuid.eq(UUID::from(&token_uuid)))
+            .execute(&mut get_conn(context))
+            .context("Could not revoke API token")?;
+        Ok(())
+    }
+
+    /// The user and scopes of a bearer token, `None` if it is malformed, unknown or revoked
+    pub fn authenticate(
+        context: &GraphQLContext,
+        token: &str,
+    ) -> Result<Option<(User, Vec<ApiScope>)>> {
+        let Some((token_uuid, secret)) = parse_token(token) else {
+            return Ok(None);
+        };
+
+        let found = api_tokens::table
+            .inner_join(users::table)
+            .filter(api_tokens::uuid.eq(UUID::from(&token_uuid)))
+            .select((ApiToken::as_select(), User::as_select()))
+            .first::<(ApiToken, User)>(&mut get_conn(context))
+            .optional()
+            .context("Could not load API token")?;
+
+        match found {
+            Some((api_token, user)) if secret_matches(secret, &api_token.token_hash) => {
+                diesel::update(api_tokens::table)
+                    .filter(api_tokens::uuid.eq(api_token.uuid))
+                    .set(api_tokens::last_used_at.eq(chrono::Utc::now().naive_utc()))
+                    .execute(&mut get_conn(context))
+                    .context("Could not update API token")?;
+                Ok(Some((user, api_token.scope_list())))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Hex SHA-256 of a token secret. Secrets are random and long, a slow password hash would only
+/// cost CPU on every request.
+pub fn secret_digest(secret: &str) -> String {
+    Sha256::digest(secret.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Whether `secret` belongs to the token with `token_hash`, digests are compared in constant time
+fn secret_matches(secret: &str, token_hash: &str) -> bool {
+    secret_digest(secret)
+        .as_bytes()
+        .ct_eq(token_hash.as_bytes())
+        .into()
+}
+
+/// Split a token string into the token's id and its secret
+fn parse_token(token: &str) -> Option<(Uuid, &str)> {
+    let (id, secret) = token.strip_prefix(TOKEN_PREFIX)?.split_once('_')?;
+    Some((Uuid::try_parse(id).ok()?, secret))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_tokens() {
+        let id = Uuid::now_v7();
+        let token = format!("{}{}_se_cr-et", TOKEN_PREFIX, id.simple());
+        assert_eq!(parse_token(&token), Some((id, "se_cr-et")));
+        assert_eq!(parse_token("Bearer nonsense"), None);
+        assert_eq!(parse_token("imt_not-a-uuid_secret"), None);
+    }
+
+    #[test]
+    fn it_matches_secrets_by_digest() {
+        let token_hash = secret_digest("se_cr-et");
+        assert!(secret_matches("se_cr-et", &token_hash));
+        assert!(!secret_matches("se_cr-eT", &token_hash));
+        assert!(!secret_matches("se_cr-et", "not a digest"));
+    }
+}
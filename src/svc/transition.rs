@@ -0,0 +1,168 @@
+use crate::{
+    config::ConfigSvc,
+    context::GraphQLContext,
+    models::Transition,
+    svc::upload::{RGB_HEIGHT, RGB_WIDTH},
+};
+use anyhow::Result;
+use std::time::Duration;
+
+/// Longest transition accepted by `setTransition`
+pub const MAX_TRANSITION_MS: u64 = 5000;
+
+/// Frame rate a transition aims for when the device keeps up
+const TARGET_FPS: u64 = 25;
+
+/// Fewer intermediate frames than this look like stutter, cut instead
+const MIN_FRAMES: u64 = 3;
+
+pub struct TransitionSvc {}
+
+impl TransitionSvc {
+    /// Store the transition type and optionally its duration
+    pub async fn set(
+        context: &GraphQLContext,
+        transition: Transition,
+        duration_ms: Option<u64>,
+    ) -> Result<()> {
+        if let Some(duration_ms) = duration_ms {
+            if duration_ms == 0 || duration_ms > MAX_TRANSITION_MS {
+                anyhow::bail!(
+                    "Transition duration must be between 1 and {} ms",
+                    MAX_TRANSITION_MS
+                );
+            }
+            ConfigSvc::set_transition_duration_ms(context, duration_ms)
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to set transition duration: {}", e))?;
+        }
+
+        ConfigSvc::set_transition(context, transition)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to set transition: {}", e))?;
+        Ok(())
+    }
+}
+
+/// Number of intermediate frames to push for a transition, 0 means cut. `frame_time` is how
+/// long the device took per frame so far, a device too slow for a smooth transition gets a cut.
+pub fn frame_count(
+    transition: Transition,
+    duration: Duration,
+    frame_time: Option<Duration>,
+) -> u64 {
+    if transition == Transition::Cut {
+        return 0;
+    }
+
+    let mut frames = duration.as_millis() as u64 * TARGET_FPS / 1000;
+    if let Some(frame_time) = frame_time.filter(|frame_time| !frame_time.is_zero()) {
+        frames = frames.min((duration.as_millis() / frame_time.as_millis().max(1)) as u64);
+    }
+    if frames < MIN_FRAMES {
+        0
+    } else {
+        frames
+    }
+}
+
+/// Running estimate of the device's time per frame
+pub fn smoothed_frame_time(previous: Option<Duration>, sample: Duration) -> Duration {
+    match previous {
+        Some(previous) => (previous * 3 + sample) / 4,
+        None => sample,
+    }
+}
+
+/// One raw rgb8 frame of a transition, `progress` runs from 0 (`from`) to 1 (`to`)
+pub fn transition_frame(transition: Transition, from: &[u8], to: &[u8], progress: f32) -> Vec<u8> {
+    let progress = progress.clamp(0.0, 1.0);
+    let row_len = RGB_WIDTH * 3;
+    match transition {
+        Transition::Cut => to.to_vec(),
+        Transition::Crossfade => from
+            .iter()
+            .zip(to)
+            .map(|(a, b)| (*a as f32 + (*b as f32 - *a as f32) * progress).round() as u8)
+            .collect(),
+        Transition::Wipe => {
+            let split = (RGB_WIDTH as f32 * progress).round() as usize * 3;
+            let mut frame = from.to_vec();
+            for row in 0..RGB_HEIGHT {
+                let start = row * row_len;
+                frame[start..start + split].copy_from_slice(&to[start..start + split]);
+            }
+            frame
+        }
+        Transition::Slide => {
+            let offset = (RGB_WIDTH as f32 * progress).round() as usize * 3;
+            let mut frame = Vec::with_capacity(from.len());
+            for row in 0..RGB_HEIGHT {
+                let start = row * row_len;
+                frame.extend_from_slice(&from[start + offset..start + row_len]);
+                frame.extend_from_slice(&to[start..start + offset]);
+            }
+            frame
+        }
+    }
+}
+
+/// Whether a frame has the size the RGB device expects, transitions need two of them
+pub fn is_full_frame(frame: &[u8]) -> bool {
+    frame.len() == RGB_WIDTH * RGB_HEIGHT * 3
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid(value: u8) -> Vec<u8> {
+        vec![value; RGB_WIDTH * RGB_HEIGHT * 3]
+    }
+
+    #[test]
+    fn it_crossfades_halfway() {
+        let frame = transition_frame(Transition::Crossfade, &solid(0), &solid(200), 0.5);
+        assert!(frame.iter().all(|channel| *channel == 100));
+    }
+
+    #[test]
+    fn it_wipes_and_slides_by_column() {
+        let from = solid(0);
+        let to = solid(255);
+        for transition in [Transition::Wipe, Transition::Slide] {
+            let frame = transition_frame(transition, &from, &to, 0.25);
+            assert_eq!(frame.len(), from.len());
+            let row = &frame[..RGB_WIDTH * 3];
+            let new_columns = row.chunks(3).filter(|pixel| pixel[0] == 255).count();
+            assert_eq!(new_columns, RGB_WIDTH / 4);
+        }
+    }
+
+    #[test]
+    fn it_ends_on_the_new_frame() {
+        let to = solid(42);
+        for transition in [Transition::Crossfade, Transition::Wipe, Transition::Slide] {
+            assert_eq!(transition_frame(transition, &solid(7), &to, 1.0), to);
+        }
+    }
+
+    #[test]
+    fn it_falls_back_to_a_cut_on_slow_devices() {
+        let duration = Duration::from_millis(500);
+        assert_eq!(frame_count(Transition::Crossfade, duration, None), 12);
+        assert_eq!(
+            frame_count(Transition::Wipe, duration, Some(Duration::from_millis(100))),
+            5
+        );
+        assert_eq!(
+            frame_count(
+                Transition::Slide,
+                duration,
+                Some(Duration::from_millis(200))
+            ),
+            0
+        );
+        assert_eq!(frame_count(Transition::Cut, duration, None), 0);
+    }
+}
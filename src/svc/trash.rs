@@ -0,0 +1,42 @@
+use crate::{config::ConfigSvc, context::GraphQLContext, svc::UploadSvc};
+use anyhow::Result;
+use std::{sync::Arc, time::Duration};
+use tokio::time::sleep;
+use tracing::{error, info, warn};
+
+/// How often the trash is checked for uploads past their retention
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+pub struct TrashSvc;
+
+impl TrashSvc {
+    /// Start the background task that permanently removes expired trash
+    pub fn start(context: Arc<GraphQLContext>) {
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = Self::purge_expired(&context).await {
+                    error!("Failed to purge trash: {}", e);
+                }
+                sleep(PURGE_INTERVAL).await;
+            }
+        });
+    }
+
+    /// Permanently delete uploads that have been in the trash longer than the retention period
+    pub async fn purge_expired(context: &GraphQLContext) -> Result<usize> {
+        let retention_days = ConfigSvc::get_trash_retention_days(context)
+            .await
+            .unwrap_or_else(|e| {
+                warn!("Failed to load trash retention, using default 30 days: {}", e);
+                30
+            });
+
+        let cutoff = chrono::Utc::now().naive_utc() - chrono::Duration::days(retention_days as i64);
+        let purged = UploadSvc::purge_deleted_before(context, cutoff)?;
+        if purged > 0 {
+            info!("Purged {} uploads from the trash", purged);
+        }
+
+        Ok(purged)
+    }
+}
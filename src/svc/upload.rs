@@ -2,14 +2,19 @@ use crate::{
     context::GraphQLContext,
     db::get_conn,
     get_env_typed,
-    models::{DisplayFormat, Upload},
-    schema::uploads,
+    models::{DisplayFormat, DisplayTrigger, ModerationStatus, Upload},
+    schema::{album_uploads, playlist_items, schedule_rules, share_links, upload_tags, uploads},
+    svc::{
+        content_hash, DuplicateSvc, Event, LimitSvc, ModerationSvc, TagSvc, MAX_WEIGHT,
+        PACKED_MONO_BYTES,
+    },
     uuid::UUID,
 };
 use anyhow::{Context, Result};
 use cached::proc_macro::cached;
+use chrono::NaiveDateTime;
 use diesel::prelude::*;
-use image::{ImageBuffer, ImageEncoder, ImageReader, Luma};
+use image::{ImageBuffer, ImageEncoder, ImageReader, Luma, RgbImage};
 use reqwest::Client;
 use uuid::Uuid;
 
@@ -19,16 +24,70 @@ impl UploadSvc {
     pub fn get(context: &GraphQLContext, upload_uuid: Uuid) -> Result<Upload> {
         uploads::table
             .filter(uploads::uuid.eq(UUID::from(&upload_uuid)))
+            .filter(uploads::deleted_at.is_null())
             // .select(Upload::as_select())
             .first(&mut get_conn(context))
             .context("Could not find upload")
     }
-    pub fn list(context: &GraphQLContext, limit: i32, offset: i32) -> Result<Vec<Upload>> {
+    /// Like `get`, but private uploads of other users look like they don't exist
+    pub fn get_visible(context: &GraphQLContext, upload_uuid: Uuid) -> Result<Upload> {
+        let upload = Self::get(context, upload_uuid)?;
+        if !upload.visible_to(context.user.as_ref()) {
+            anyhow::bail!("Could not find upload");
+        }
+        Ok(upload)
+    }
+    /// Like `get_visible`, but only for uploads the user may change
+    pub fn get_managed(context: &GraphQLContext, upload_uuid: Uuid) -> Result<Upload> {
+        let upload = Self::get_visible(context, upload_uuid)?;
+        if !upload.manageable_by(context.user.as_ref()) {
+            anyhow::bail!("Only the owner of an upload or an admin can change it");
+        }
+        Ok(upload)
+    }
+    pub fn get_trashed(context: &GraphQLContext, upload_uuid: Uuid) -> Result<Upload> {
+        uploads::table
+            .filter(uploads::uuid.eq(UUID::from(&upload_uuid)))
+            .filter(uploads::deleted_at.is_not_null())
+            .first(&mut get_conn(context))
+            .context("Could not find upload in trash")
+    }
+    pub fn list(
+        context: &GraphQLContext,
+        limit: i32,
+        offset: i32,
+        tag: Option<&str>,
+        album_uuid: Option<Uuid>,
+    ) -> Result<Vec<Upload>> {
         let limit: i64 = limit.into();
         let offset: i64 = offset.into();
 
-        uploads::table
+        let mut query = uploads::table
             .filter(uploads::public.eq(true))
+            .filter(uploads::status.eq(ModerationStatus::Approved.as_str()))
+            .filter(uploads::deleted_at.is_null())
+            .into_boxed();
+
+        if let Some(tag) = tag {
+            query = query.filter(
+                uploads::uuid.eq_any(
+                    upload_tags::table
+                        .filter(upload_tags::tag.eq(TagSvc::normalize(tag)))
+                        .select(upload_tags::upload_uuid),
+                ),
+            );
+        }
+        if let Some(album_uuid) = album_uuid {
+            query = query.filter(
+                uploads::uuid.eq_any(
+                    album_uploads::table
+                        .filter(album_uploads::album_uuid.eq(UUID::from(&album_uuid)))
+                        .select(album_uploads::upload_uuid),
+                ),
+            );
+        }
+
+        query
             .select(Upload::as_select())
             .order_by(uploads::uuid.desc())
             .limit(limit)
@@ -36,40 +95,250 @@ impl UploadSvc {
             .load::<Upload>(&mut get_conn(context))
             .context("Could not load uploads")
     }
+    /// Uploads of `owner_uuid`, public and private, newest first
+    pub fn list_owned(
+        context: &GraphQLContext,
+        owner_uuid: Uuid,
+        limit: i32,
+        offset: i32,
+    ) -> Result<Vec<Upload>> {
+        let limit: i64 = limit.into();
+        let offset: i64 = offset.into();
+
+        uploads::table
+            .filter(uploads::owner_uuid.eq(UUID::from(&owner_uuid)))
+            .filter(uploads::deleted_at.is_null())
+            .select(Upload::as_select())
+            .order_by(uploads::uuid.desc())
+            .limit(limit)
+            .offset(offset)
+            .load::<Upload>(&mut get_conn(context))
+            .context("Could not load uploads")
+    }
+    /// Trashed uploads, only those of `owner_uuid` when given
+    pub fn list_trash(
+        context: &GraphQLContext,
+        owner_uuid: Option<Uuid>,
+        limit: i32,
+        offset: i32,
+    ) -> Result<Vec<Upload>> {
+        let limit: i64 = limit.into();
+        let offset: i64 = offset.into();
+
+        let mut query = uploads::table
+            .filter(uploads::deleted_at.is_not_null())
+            .into_boxed();
+        if let Some(owner_uuid) = owner_uuid {
+            query = query.filter(uploads::owner_uuid.eq(UUID::from(&owner_uuid)));
+        }
+
+        query
+            .select(Upload::as_select())
+            .order_by(uploads::deleted_at.desc())
+            .limit(limit)
+            .offset(offset)
+            .load::<Upload>(&mut get_conn(context))
+            .context("Could not load trashed uploads")
+    }
+    /// Store and show a new upload, it belongs to the logged in user if there is one. With
+    /// moderation on it waits in the queue instead of being shown. An identical upload that
+    /// already exists is handled by the duplicate policy, which may return it instead.
     pub async fn create(context: &GraphQLContext, upload: &Upload) -> Result<Upload> {
+        LimitSvc::check_upload(context, upload.data.len()).await?;
+
+        let mut upload = Upload {
+            owner_uuid: upload
+                .owner_uuid
+                .or(context.user.as_ref().map(|user| user.uuid)),
+            content_hash: Some(content_hash(&upload.data)),
+            ..upload.clone()
+        };
+        if let Some(existing) = DuplicateSvc::check(context, &upload).await? {
+            return Ok(existing);
+        }
+        if ModerationSvc::requires_review(context).await? {
+            upload.status = ModerationStatus::Pending.as_str().to_owned();
+        }
         diesel::insert_into(uploads::table)
-            .values(upload)
+            .values(&upload)
             .execute(&mut get_conn(context))
             .context("Could not update upload")?;
 
-        push_upload_to_device(upload).await?;
+        if upload.is_approved() {
+            Self::show_new(context, &upload).await?;
+        }
+
+        let created = Self::get(context, upload.uuid.into())?;
+        context
+            .events
+            .publish(Event::UploadCreated(Box::new(created.clone())));
+        Ok(created)
+    }
+    /// Put a newly available upload on its display and let the slideshows know about it
+    pub async fn show_new(context: &GraphQLContext, upload: &Upload) -> Result<()> {
+        // Go through the display's slideshow when there is one so it can transition to the upload
+        match upload
+            .display_format()
+            .and_then(|display| context.screensaver_for(display))
+        {
+            Some(screensaver) => {
+                screensaver
+                    .push_to_device(upload, DisplayTrigger::NewUpload)
+                    .await?
+            }
+            None => {
+                let pushed = push_upload_to_device(upload).await;
+                if let Some(display) = upload.display_format() {
+                    context.events.record_device_push(display, &pushed);
+                }
+                pushed?;
+            }
+        }
 
         // Notify screensaver of new upload
-        if let Some(screensaver) = &context.screensaver {
+        for screensaver in context.screensavers() {
             if let Err(e) = screensaver.on_new_upload(upload).await {
                 tracing::warn!("Failed to notify screensaver of new upload: {}", e);
             }
         }
-
-        Self::get(context, upload.uuid.into())
+        Ok(())
     }
-    pub fn update(context: &GraphQLContext, upload: &Upload) -> Result<Upload> {
+    /// Store the editable fields of an upload: its name, message, visibility and display. An
+    /// upload that moved to another display leaves the slideshow of the old one.
+    pub async fn update(context: &GraphQLContext, upload: &Upload) -> Result<Upload> {
+        let existing = Self::get(context, upload.uuid.into())?;
+
+        // Set explicitly, the changeset of `Upload` would skip a cleared name or message
         diesel::update(uploads::table)
             .filter(uploads::uuid.eq(&upload.uuid))
-            .set(upload)
+            .set((
+                uploads::name.eq(&upload.name),
+                uploads::message.eq(&upload.message),
+                uploads::public.eq(upload.public),
+                uploads::display.eq(&upload.display),
+            ))
             .execute(&mut get_conn(context))
             .context("Could not update upload")?;
 
-        Self::get(context, upload.uuid.into())
+        let updated = Self::get(context, upload.uuid.into())?;
+        if updated.display != existing.display {
+            // The old display's slideshow drops it as if it was deleted, the new one picks it up
+            // as if it was restored
+            for screensaver in context.screensavers() {
+                if let Err(e) = screensaver.on_upload_deleted(&existing).await {
+                    tracing::warn!("Failed to notify screensaver of moved upload: {}", e);
+                }
+                if let Err(e) = screensaver.on_upload_restored(&updated).await {
+                    tracing::warn!("Failed to notify screensaver of moved upload: {}", e);
+                }
+            }
+        }
+
+        Ok(updated)
     }
-    pub fn delete(context: &GraphQLContext, upload_uuid: Uuid) -> Result<()> {
-        diesel::delete(uploads::table)
+    /// Move an upload to the trash. It stays restorable until the trash is purged.
+    pub async fn delete(context: &GraphQLContext, upload_uuid: Uuid) -> Result<()> {
+        let upload = Self::get(context, upload_uuid)?;
+
+        diesel::update(uploads::table)
             .filter(uploads::uuid.eq(UUID::from(&upload_uuid)))
+            .set(uploads::deleted_at.eq(Some(chrono::Utc::now().naive_utc())))
             .execute(&mut get_conn(context))
             .context("Could not delete upload")?;
 
+        // Notify screensaver so it can drop the upload from rotation
+        for screensaver in context.screensavers() {
+            if let Err(e) = screensaver.on_upload_deleted(&upload).await {
+                tracing::warn!("Failed to notify screensaver of deleted upload: {}", e);
+            }
+        }
+
         Ok(())
     }
+    pub async fn restore(context: &GraphQLContext, upload_uuid: Uuid) -> Result<Upload> {
+        Self::get_trashed(context, upload_uuid)?;
+
+        diesel::update(uploads::table)
+            .filter(uploads::uuid.eq(UUID::from(&upload_uuid)))
+            .set(uploads::deleted_at.eq(None::<NaiveDateTime>))
+            .execute(&mut get_conn(context))
+            .context("Could not restore upload")?;
+
+        let upload = Self::get(context, upload_uuid)?;
+
+        for screensaver in context.screensavers() {
+            if let Err(e) = screensaver.on_upload_restored(&upload).await {
+                tracing::warn!("Failed to notify screensaver of restored upload: {}", e);
+            }
+        }
+
+        Ok(upload)
+    }
+    /// Set how often an upload comes up in the weighted slideshow
+    pub fn set_weight(context: &GraphQLContext, upload_uuid: Uuid, weight: i32) -> Result<Upload> {
+        if !(1..=MAX_WEIGHT).contains(&weight) {
+            anyhow::bail!("Weight must be between 1 and {}", MAX_WEIGHT);
+        }
+        Self::get(context, upload_uuid)?;
+
+        diesel::update(uploads::table)
+            .filter(uploads::uuid.eq(UUID::from(&upload_uuid)))
+            .set(uploads::weight.eq(weight))
+            .execute(&mut get_conn(context))
+            .context("Could not set upload weight")?;
+
+        Self::get(context, upload_uuid)
+    }
+    pub fn set_favorite(
+        context: &GraphQLContext,
+        upload_uuid: Uuid,
+        favorite: bool,
+    ) -> Result<Upload> {
+        Self::get(context, upload_uuid)?;
+
+        diesel::update(uploads::table)
+            .filter(uploads::uuid.eq(UUID::from(&upload_uuid)))
+            .set(uploads::favorite.eq(favorite))
+            .execute(&mut get_conn(context))
+            .context("Could not set upload favorite")?;
+
+        Self::get(context, upload_uuid)
+    }
+    /// Permanently remove uploads that were trashed before the cutoff
+    pub fn purge_deleted_before(context: &GraphQLContext, cutoff: NaiveDateTime) -> Result<usize> {
+        let mut conn = get_conn(context);
+        conn.transaction(|conn| {
+            let expired = || {
+                uploads::table
+                    .filter(uploads::deleted_at.lt(cutoff))
+                    .select(uploads::uuid)
+            };
+
+            diesel::delete(album_uploads::table)
+                .filter(album_uploads::upload_uuid.eq_any(expired()))
+                .execute(conn)?;
+            diesel::delete(upload_tags::table)
+                .filter(upload_tags::upload_uuid.eq_any(expired()))
+                .execute(conn)?;
+            diesel::delete(playlist_items::table)
+                .filter(playlist_items::upload_uuid.eq_any(expired()))
+                .execute(conn)?;
+            diesel::delete(share_links::table)
+                .filter(share_links::upload_uuid.eq_any(expired()))
+                .execute(conn)?;
+            diesel::delete(schedule_rules::table)
+                .filter(
+                    schedule_rules::upload_uuid
+                        .assume_not_null()
+                        .eq_any(expired()),
+                )
+                .execute(conn)?;
+            diesel::delete(uploads::table)
+                .filter(uploads::deleted_at.lt(cutoff))
+                .execute(conn)
+        })
+        .context("Could not purge trashed uploads")
+    }
 }
 
 // convert our bit-packed upload data to a PNG file using the Image crate. Each bit represents a pixel: 0 = white, 1 =
@@ -115,27 +384,18 @@ pub async fn packed_to_png(data: Vec<u8>) -> Vec<u8> {
 }
 
 pub async fn push_upload_to_device(upload: &Upload) -> Result<()> {
-    let client = Client::new();
+    if upload.deleted_at.is_some() {
+        anyhow::bail!("Upload is in the trash");
+    }
+
     if upload.display.as_deref() == Some(DisplayFormat::RGB320x240.as_str()) {
-        let data = ImageReader::new(std::io::Cursor::new(upload.data.clone()));
-        let img = data
-            .with_guessed_format()
-            .context("Could not guess image format")?
-            .decode()
-            .context("Could not decode image")?;
-
-        client
-            .post(get_env_typed("ESP_RGB_ENDPOINT", "".to_owned()))
-            .body(img.to_rgb8().into_raw())
-            .send()
-            .await
-            .context("Could not send to RGB device")?;
+        push_rgb_frame(decode_rgb(upload)?.into_raw()).await?;
     } else {
-        if upload.data.len() > 1025 {
+        if upload.data.len() > PACKED_MONO_BYTES {
             anyhow::bail!("Upload data too large to push to device");
         }
 
-        client
+        Client::new()
             .post(get_env_typed("ESP_ENDPOINT", "".to_owned()))
             .body(upload.data.clone())
             .send()
@@ -145,3 +405,69 @@ pub async fn push_upload_to_device(upload: &Upload) -> Result<()> {
 
     Ok(())
 }
+
+/// Size of the frames the RGB device expects
+pub const RGB_WIDTH: usize = 320;
+pub const RGB_HEIGHT: usize = 240;
+
+/// Decode an RGB upload into the raw pixels the RGB device expects
+pub fn decode_rgb(upload: &Upload) -> Result<RgbImage> {
+    let data = ImageReader::new(std::io::Cursor::new(upload.data.clone()));
+    let img = data
+        .with_guessed_format()
+        .context("Could not guess image format")?
+        .decode()
+        .context("Could not decode image")?;
+    Ok(img.to_rgb8())
+}
+
+/// Send a raw rgb8 frame to the RGB device
+pub async fn push_rgb_frame(frame: Vec<u8>) -> Result<()> {
+    Client::new()
+        .post(get_env_typed("ESP_RGB_ENDPOINT", "".to_owned()))
+        .body(frame)
+        .send()
+        .await
+        .context("Could not send to RGB device")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn it_clears_the_name_and_message() {
+        let context = GraphQLContext::in_memory();
+        let mut upload = Upload {
+            uuid: UUID::random(),
+            message: Some("Hello".to_owned()),
+            data: vec![0; PACKED_MONO_BYTES],
+            public: true,
+            uploaded_at: Some(chrono::Utc::now().naive_utc()),
+            name: Some("Lobby".to_owned()),
+            display: Some(DisplayFormat::Esp32.to_string()),
+            deleted_at: None,
+            weight: 1,
+            favorite: false,
+            owner_uuid: None,
+            status: ModerationStatus::Approved.as_str().to_owned(),
+            moderated_by: None,
+            moderated_at: None,
+            rejection_reason: None,
+            content_hash: None,
+        };
+        diesel::insert_into(uploads::table)
+            .values(&upload)
+            .execute(&mut get_conn(&context))
+            .unwrap();
+
+        upload.name = None;
+        upload.message = None;
+        UploadSvc::update(&context, &upload).await.unwrap();
+
+        let reloaded = UploadSvc::get(&context, upload.uuid.into()).unwrap();
+        assert_eq!(reloaded.name, None);
+        assert_eq!(reloaded.message, None);
+    }
+}
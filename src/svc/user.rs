@@ -0,0 +1,199 @@
+use crate::{
+    context::GraphQLContext,
+    db::get_conn,
+    get_env,
+    models::{User, UserInput},
+    schema::{api_tokens, sessions, uploads, users},
+    uuid::UUID,
+};
+use anyhow::{Context, Result};
+use argon2::{
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use diesel::prelude::*;
+use lazy_static::lazy_static;
+use tracing::info;
+use uuid::Uuid;
+
+/// Shortest password accepted for an account
+pub const MIN_PASSWORD_LEN: usize = 8;
+
+/// Logins a client IP may attempt per minute, to slow down password guessing
+pub const LOGIN_ATTEMPTS_PER_MINUTE: u32 = 10;
+
+lazy_static! {
+    /// Checked when a username doesn't exist, so failing takes as long as with a wrong password
+    static ref DUMMY_PASSWORD_HASH: String =
+        hash_password("not anyone's password").expect("Could not hash dummy password");
+}
+
+pub struct UserSvc {}
+
+impl UserSvc {
+    pub fn get(context: &GraphQLContext, user_uuid: Uuid) -> Result<User> {
+        users::table
+            .filter(users::uuid.eq(UUID::from(&user_uuid)))
+            .first(&mut get_conn(context))
+            .context("Could not find user")
+    }
+    pub fn list(context: &GraphQLContext) -> Result<Vec<User>> {
+        users::table
+            .order_by(users::username.asc())
+            .load::<User>(&mut get_conn(context))
+            .context("Could not load users")
+    }
+    pub fn create(context: &GraphQLContext, input: UserInput) -> Result<User> {
+        let username = input.username.trim().to_owned();
+        if username.is_empty() {
+            anyhow::bail!("Username cannot be empty");
+        }
+        let user = User {
+            uuid: UUID::random(),
+            username,
+            password_hash: hash_password(&input.password)?,
+            is_admin: input.is_admin.unwrap_or(false),
+            created_at: Some(chrono::Utc::now().naive_utc()),
+            trusted: input.trusted.unwrap_or(false),
+            storage_quota_mb: None,
+        };
+
+        diesel::insert_into(users::table)
+            .values(&user)
+            .execute(&mut get_conn(context))
+            .context("Could not create user, the username may be taken")?;
+
+        Self::get(context, user.uuid.into())
+    }
+    pub fn delete(context: &GraphQLContext, user_uuid: Uuid) -> Result<()> {
+        let mut conn = get_conn(context);
+        conn.transaction(|conn| {
+            diesel::delete(sessions::table)
+                .filter(sessions::user_uuid.eq(UUID::from(&user_uuid)))
+                .execute(conn)?;
+            diesel::delete(api_tokens::table)
+                .filter(api_tokens::user_uuid.eq(UUID::from(&user_uuid)))
+                .execute(conn)?;
+            // Their uploads stay, without an owner only admins can manage them
+            diesel::update(uploads::table)
+                .filter(uploads::owner_uuid.eq(UUID::from(&user_uuid)))
+                .set(uploads::owner_uuid.eq(None::<UUID>))
+                .execute(conn)?;
+            diesel::delete(users::table)
+                .filter(users::uuid.eq(UUID::from(&user_uuid)))
+                .execute(conn)
+        })
+        .context("Could not delete user")?;
+        Ok(())
+    }
+
+    /// Replace a user's password and log out all of their sessions
+    pub fn set_password(context: &GraphQLContext, user_uuid: Uuid, password: &str) -> Result<()> {
+        let password_hash = hash_password(password)?;
+        let mut conn = get_conn(context);
+        conn.transaction(|conn| {
+            diesel::update(users::table)
+                .filter(users::uuid.eq(UUID::from(&user_uuid)))
+                .set(users::password_hash.eq(password_hash))
+                .execute(conn)?;
+            diesel::delete(sessions::table)
+                .filter(sessions::user_uuid.eq(UUID::from(&user_uuid)))
+                .execute(conn)
+        })
+        .context("Could not change password")?;
+        Ok(())
+    }
+
+    /// Check a username and password, the error doesn't reveal which of them was wrong
+    pub fn authenticate(context: &GraphQLContext, username: &str, password: &str) -> Result<User> {
+        let user = users::table
+            .filter(users::username.eq(username.trim()))
+            .first::<User>(&mut get_conn(context))
+            .optional()
+            .context("Could not load user")?;
+
+        match user {
+            Some(user) if verify_password(password, &user.password_hash) => Ok(user),
+            Some(_) => anyhow::bail!("Invalid username or password"),
+            None => {
+                // Don't let the response time tell which usernames exist
+                verify_password(password, &DUMMY_PASSWORD_HASH);
+                anyhow::bail!("Invalid username or password")
+            }
+        }
+    }
+
+    /// Create the first admin from `ADMIN_USERNAME` and `ADMIN_PASSWORD` when there are no users
+    pub fn bootstrap_admin(context: &GraphQLContext) -> Result<()> {
+        let username = get_env("ADMIN_USERNAME", "");
+        let password = get_env("ADMIN_PASSWORD", "");
+        if username.is_empty() || password.is_empty() {
+            return Ok(());
+        }
+
+        let count: i64 = users::table
+            .count()
+            .get_result(&mut get_conn(context))
+            .context("Could not count users")?;
+        if count == 0 {
+            Self::create(
+                context,
+                UserInput {
+                    username,
+                    password,
+                    is_admin: Some(true),
+                    trusted: None,
+                },
+            )?;
+            info!("Created admin account from ADMIN_USERNAME");
+        }
+        Ok(())
+    }
+}
+
+/// Check the password policy and hash the password with argon2 and a random salt
+pub fn hash_password(password: &str) -> Result<String> {
+    if password.chars().count() < MIN_PASSWORD_LEN {
+        anyhow::bail!(
+            "Password must be at least {} characters long",
+            MIN_PASSWORD_LEN
+        );
+    }
+
+    let salt = SaltString::encode_b64(&rand::random::<[u8; 16]>())
+        .map_err(|e| anyhow::anyhow!("Could not generate salt: {}", e))?;
+    let hash = Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|e| anyhow::anyhow!("Could not hash password: {}", e))?;
+    Ok(hash.to_string())
+}
+
+/// Whether `password` matches an argon2 hash made by `hash_password`
+pub fn verify_password(password: &str, password_hash: &str) -> bool {
+    PasswordHash::new(password_hash)
+        .map(|hash| {
+            Argon2::default()
+                .verify_password(password.as_bytes(), &hash)
+                .is_ok()
+        })
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_verifies_hashed_passwords() {
+        let hash = hash_password("correct horse").unwrap();
+        assert_ne!(hash, "correct horse");
+        assert!(verify_password("correct horse", &hash));
+        assert!(!verify_password("battery staple", &hash));
+        assert!(!verify_password("correct horse", "not a hash"));
+    }
+
+    #[test]
+    fn it_rejects_short_passwords() {
+        assert!(hash_password("short").is_err());
+    }
+}
@@ -0,0 +1,177 @@
+use crate::{
+    models::{DisplayFormat, Upload},
+    svc::upload::{RGB_HEIGHT, RGB_WIDTH},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use image::ImageReader;
+use serde::Serialize;
+use std::{fmt, io::Cursor};
+
+/// Size of packed Esp32 data: 128x64 pixels at one bit each
+pub const PACKED_MONO_BYTES: usize = 128 * 64 / 8;
+
+/// Largest encoded image accepted for the RGB display
+pub const MAX_RGB_BYTES: usize = 2 * 1024 * 1024;
+
+pub const MAX_NAME_CHARS: usize = 100;
+pub const MAX_MESSAGE_CHARS: usize = 500;
+
+/// What is wrong with one input field
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldViolation {
+    pub field: &'static str,
+    pub message: String,
+}
+
+impl FieldViolation {
+    pub fn new(field: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            field,
+            message: message.into(),
+        }
+    }
+}
+
+/// An upload was turned away before it was stored, with every field that is wrong
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError(pub Vec<FieldViolation>);
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid upload")?;
+        for violation in &self.0 {
+            write!(f, "; {}: {}", violation.field, violation.message)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Decode the base64 `data` field of an upload input
+pub fn decode_data(data: &str) -> Result<Vec<u8>, FieldViolation> {
+    STANDARD
+        .decode(data.trim())
+        .map_err(|e| FieldViolation::new("data", format!("Not valid base64: {e}")))
+}
+
+/// Check an upload before it is stored, its data has to suit the display it is meant for
+pub fn validate_upload(upload: &Upload) -> Result<(), ValidationError> {
+    let mut violations = Vec::new();
+    if let Some(violation) = check_length("name", upload.name.as_deref(), MAX_NAME_CHARS) {
+        violations.push(violation);
+    }
+    if let Some(violation) = check_length("message", upload.message.as_deref(), MAX_MESSAGE_CHARS) {
+        violations.push(violation);
+    }
+    match upload.display_format() {
+        Some(display) => {
+            if let Err(message) = check_data(display, &upload.data) {
+                violations.push(FieldViolation::new("data", message));
+            }
+        }
+        None => violations.push(FieldViolation::new("display", "Unknown display format")),
+    }
+
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(ValidationError(violations))
+    }
+}
+
+fn check_length(field: &'static str, value: Option<&str>, max: usize) -> Option<FieldViolation> {
+    let count = value?.chars().count();
+    (count > max).then(|| {
+        FieldViolation::new(
+            field,
+            format!("Must be at most {max} characters, got {count}"),
+        )
+    })
+}
+
+/// Packed mono data needs the exact frame size, RGB data has to be an image of the display's size
+fn check_data(display: DisplayFormat, data: &[u8]) -> Result<(), String> {
+    if data.is_empty() {
+        return Err("Upload data is empty".to_owned());
+    }
+
+    match display {
+        DisplayFormat::Esp32 => {
+            if data.len() != PACKED_MONO_BYTES {
+                return Err(format!(
+                    "Packed Esp32 data must be exactly {} bytes (128x64 pixels at 1 bit), got {}",
+                    PACKED_MONO_BYTES,
+                    data.len()
+                ));
+            }
+        }
+        DisplayFormat::RGB320x240 => {
+            if data.len() > MAX_RGB_BYTES {
+                return Err(format!(
+                    "Images for the RGB display can be at most {} KB, got {} KB",
+                    MAX_RGB_BYTES / 1024,
+                    data.len().div_ceil(1024)
+                ));
+            }
+            let reader = ImageReader::new(Cursor::new(data))
+                .with_guessed_format()
+                .map_err(|e| format!("Could not read image: {e}"))?;
+            if reader.format().is_none() {
+                return Err("Not an image format the server can read".to_owned());
+            }
+            // Check the header first so oversized images are turned away before decoding
+            let (width, height) = reader
+                .into_dimensions()
+                .map_err(|e| format!("Could not read image: {e}"))?;
+            if (width as usize, height as usize) != (RGB_WIDTH, RGB_HEIGHT) {
+                return Err(format!(
+                    "Image must be {RGB_WIDTH}x{RGB_HEIGHT} pixels, got {width}x{height}"
+                ));
+            }
+            ImageReader::new(Cursor::new(data))
+                .with_guessed_format()
+                .map_err(|e| format!("Could not read image: {e}"))?
+                .decode()
+                .map_err(|e| format!("Could not decode image: {e}"))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageFormat, RgbImage};
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut data = Vec::new();
+        RgbImage::new(width, height)
+            .write_to(&mut Cursor::new(&mut data), ImageFormat::Png)
+            .unwrap();
+        data
+    }
+
+    #[test]
+    fn it_checks_packed_mono_length() {
+        assert!(check_data(DisplayFormat::Esp32, &[0; PACKED_MONO_BYTES]).is_ok());
+        assert!(check_data(DisplayFormat::Esp32, &[0; PACKED_MONO_BYTES + 1]).is_err());
+        assert!(check_data(DisplayFormat::Esp32, &[]).is_err());
+    }
+
+    #[test]
+    fn it_checks_rgb_images() {
+        assert!(check_data(DisplayFormat::RGB320x240, &png(320, 240)).is_ok());
+        assert_eq!(
+            check_data(DisplayFormat::RGB320x240, &png(128, 64)),
+            Err("Image must be 320x240 pixels, got 128x64".to_owned())
+        );
+        assert!(check_data(DisplayFormat::RGB320x240, b"not an image").is_err());
+    }
+
+    #[test]
+    fn it_rejects_bad_base64() {
+        assert_eq!(decode_data("AAEC"), Ok(vec![0, 1, 2]));
+        assert_eq!(decode_data("not base64!").unwrap_err().field, "data");
+    }
+}
@@ -0,0 +1,172 @@
+use crate::{models::Upload, uuid::UUID};
+use rand::{distributions::WeightedIndex, prelude::Distribution, Rng};
+
+/// Favorites count this many times their weight
+pub const FAVORITE_BOOST: i32 = 3;
+
+/// Weights accepted by `setUploadWeight`
+pub const MAX_WEIGHT: i32 = 100;
+
+/// How many picks are remembered for stepping backwards
+const HISTORY_LEN: usize = 50;
+
+impl Upload {
+    /// Weight used for weighted selection, including the favorite boost
+    pub fn effective_weight(&self) -> i32 {
+        let weight = self.weight.clamp(1, MAX_WEIGHT);
+        if self.favorite {
+            weight * FAVORITE_BOOST
+        } else {
+            weight
+        }
+    }
+}
+
+/// Playback order for weighted mode. Picks are independent weighted draws, except that the
+/// upload on screen is never picked again right away. Recent picks are kept so stepping
+/// backwards retraces what was actually shown.
+#[derive(Debug, Clone, Default)]
+pub struct WeightedOrder {
+    history: Vec<UUID>,
+    /// Index into `history` of the upload on screen, `None` before the first one
+    position: Option<usize>,
+}
+
+impl WeightedOrder {
+    /// The upload currently on screen
+    pub fn current(&self) -> Option<UUID> {
+        self.position
+            .and_then(|position| self.history.get(position).copied())
+    }
+
+    /// Move forward, replaying history after `previous` before drawing a new upload
+    pub fn next(&mut self, candidates: &[(UUID, i32)], rng: &mut impl Rng) -> Option<UUID> {
+        let next = self.position.map_or(0, |position| position + 1);
+        if let Some(idx) = (next..self.history.len())
+            .find(|idx| candidates.iter().any(|(id, _)| *id == self.history[*idx]))
+        {
+            self.position = Some(idx);
+            return self.current();
+        }
+
+        let picked = Self::pick(candidates, self.current(), rng)?;
+        if let Some(position) = self.position {
+            self.history.truncate(position + 1);
+        }
+        self.history.push(picked);
+        if self.history.len() > HISTORY_LEN {
+            self.history.remove(0);
+        }
+        self.position = Some(self.history.len() - 1);
+        Some(picked)
+    }
+
+    /// Step back through what was shown, skipping uploads that are no longer available
+    pub fn previous(&mut self, ids: &[UUID]) -> Option<UUID> {
+        let mut position = self.position?;
+        while position > 0 {
+            position -= 1;
+            if ids.contains(&self.history[position]) {
+                self.position = Some(position);
+                return self.current();
+            }
+        }
+
+        // Nothing earlier is retained, stay where we are
+        self.current()
+    }
+
+    /// Draw one upload proportionally to its weight, avoiding `current` when there's a choice
+    fn pick(candidates: &[(UUID, i32)], current: Option<UUID>, rng: &mut impl Rng) -> Option<UUID> {
+        let pool: Vec<&(UUID, i32)> = candidates
+            .iter()
+            .filter(|(id, weight)| *weight > 0 && Some(*id) != current)
+            .collect();
+        if pool.is_empty() {
+            return candidates.first().map(|(id, _)| *id);
+        }
+
+        let weights = WeightedIndex::new(pool.iter().map(|(_, weight)| *weight)).ok()?;
+        Some(pool[weights.sample(rng)].0)
+    }
+}
+
+/// Expected fraction of screen time per slide given each slide's weight and dwell time
+pub fn screen_time_shares(slides: &[(i32, u64)]) -> Vec<f64> {
+    let total: f64 = slides
+        .iter()
+        .map(|(weight, seconds)| *weight as f64 * *seconds as f64)
+        .sum();
+    slides
+        .iter()
+        .map(|(weight, seconds)| {
+            if total > 0.0 {
+                *weight as f64 * *seconds as f64 / total
+            } else {
+                0.0
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn it_favors_heavier_uploads() {
+        let heavy = UUID::random();
+        let mut candidates: Vec<(UUID, i32)> = (0..4).map(|_| (UUID::random(), 1)).collect();
+        candidates.push((heavy, 8));
+        let mut rng = StdRng::seed_from_u64(11);
+        let mut order = WeightedOrder::default();
+
+        let picks: Vec<UUID> = (0..1000)
+            .filter_map(|_| order.next(&candidates, &mut rng))
+            .collect();
+        let heavy_count = picks.iter().filter(|id| **id == heavy).count();
+        for (light, _) in &candidates[..4] {
+            let light_count = picks.iter().filter(|id| *id == light).count();
+            assert!(heavy_count > light_count * 2);
+        }
+    }
+
+    #[test]
+    fn it_never_repeats_back_to_back() {
+        let candidates = [(UUID::random(), 50), (UUID::random(), 1)];
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut order = WeightedOrder::default();
+
+        let mut last = None;
+        for _ in 0..100 {
+            let next = order.next(&candidates, &mut rng);
+            assert_ne!(next, last);
+            last = next;
+        }
+    }
+
+    #[test]
+    fn it_goes_back_and_replays_history() {
+        let candidates: Vec<(UUID, i32)> = (1..=5).map(|weight| (UUID::random(), weight)).collect();
+        let ids: Vec<UUID> = candidates.iter().map(|(id, _)| *id).collect();
+        let mut rng = StdRng::seed_from_u64(9);
+        let mut order = WeightedOrder::default();
+
+        let shown: Vec<UUID> = (0..5)
+            .map(|_| order.next(&candidates, &mut rng).unwrap())
+            .collect();
+        for expected in shown.iter().rev().skip(1) {
+            assert_eq!(order.previous(&ids), Some(*expected));
+        }
+        for expected in shown.iter().skip(1) {
+            assert_eq!(order.next(&candidates, &mut rng), Some(*expected));
+        }
+    }
+
+    #[test]
+    fn it_computes_screen_time_shares() {
+        let shares = screen_time_shares(&[(1, 60), (3, 60), (1, 120)]);
+        assert_eq!(shares, vec![60.0 / 360.0, 180.0 / 360.0, 120.0 / 360.0]);
+    }
+}